@@ -0,0 +1,101 @@
+// Compares the level broadphase against checking every box, on a level-sized
+// grid of platforms. Run with `cargo bench`.
+use frenderer::types::*;
+use jumpyball::physics::{self, BoundingBox, Colliders, Shape, Sphere};
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+const QUERIES: usize = 10_000;
+
+fn platforms(n: usize) -> Vec<BoundingBox> {
+    let side = (n as f32).sqrt().ceil() as usize;
+    (0..n)
+        .map(|i| {
+            let (x, z) = ((i % side) as f32 * 6., (i / side) as f32 * 6.);
+            let y = ((i * 7) % 13) as f32;
+            BoundingBox::new(x, x + 3., y, y + 1., z, z + 3.)
+        })
+        .collect()
+}
+
+fn probes(boxes: &[BoundingBox]) -> Vec<(Vec3, Vec3)> {
+    let extent = boxes.iter().copied().reduce(|a, b| a.union(&b)).unwrap();
+    (0..QUERIES)
+        .map(|i| {
+            let f = i as f32 / QUERIES as f32;
+            let pos = Vec3::new(
+                extent.min_x + (extent.max_x - extent.min_x) * f,
+                extent.max_y + 2. - 15. * ((i * 31) % 97) as f32 / 97.,
+                extent.min_z + (extent.max_z - extent.min_z) * ((i * 17) % 89) as f32 / 89.,
+            );
+            (pos, Vec3::new(0.2, -1.5, -0.2))
+        })
+        .collect()
+}
+
+fn time(f: impl Fn()) -> Duration {
+    let start = Instant::now();
+    f();
+    start.elapsed()
+}
+
+fn bench(n: usize) {
+    let boxes = platforms(n);
+    let probes = probes(&boxes);
+    let colliders = Colliders::new(boxes.iter().copied().map(Shape::from).collect());
+
+    let linear_sweep = time(|| {
+        for &(pos, motion) in &probes {
+            black_box(
+                boxes
+                    .iter()
+                    .filter_map(|b| physics::sweep_sphere_box(pos, motion, 1., b))
+                    .reduce(f32::min),
+            );
+        }
+    });
+    let bvh_sweep = time(|| {
+        for &(pos, motion) in &probes {
+            black_box(colliders.sweep_sphere(pos, motion, 1.));
+        }
+    });
+
+    let linear_overlap = time(|| {
+        for &(pos, _) in &probes {
+            black_box(boxes.iter().filter(|b| Sphere { pos, r: 1. }.touching(b)).count());
+        }
+    });
+    let bvh_overlap = time(|| {
+        for &(pos, _) in &probes {
+            black_box(colliders.overlapping_sphere(pos, 1.).len());
+        }
+    });
+
+    let linear_ray = time(|| {
+        for &(pos, _) in &probes {
+            let d = Vec3::new(0., -50., 0.);
+            black_box(
+                boxes
+                    .iter()
+                    .filter_map(|b| physics::ray_box(pos, d, b))
+                    .reduce(f32::min),
+            );
+        }
+    });
+    let bvh_ray = time(|| {
+        for &(pos, _) in &probes {
+            black_box(colliders.ray_cast(pos, Vec3::new(0., -50., 0.)));
+        }
+    });
+
+    println!("{n} boxes, {QUERIES} queries each:");
+    println!("  sweep    linear {:>10.2?}  bvh {:>10.2?}", linear_sweep, bvh_sweep);
+    println!("  overlap  linear {:>10.2?}  bvh {:>10.2?}", linear_overlap, bvh_overlap);
+    println!("  ray      linear {:>10.2?}  bvh {:>10.2?}", linear_ray, bvh_ray);
+}
+
+fn main() {
+    for n in [10, 100, 500, 2000] {
+        bench(n);
+    }
+}
@@ -1,7 +1,7 @@
 use crate::animation;
 use crate::color_eyre::eyre::{ensure, eyre};
 use crate::image::Image;
-use crate::renderer::{flat, skinned, textured};
+use crate::renderer::{flat, skinned, text, textured};
 use crate::types::*;
 use crate::vulkan::Vulkan;
 use crate::Result;
@@ -399,6 +399,20 @@ impl Assets {
             meshes.iter().map(|(_, m)| m).copied().collect(),
         )))
     }
+    // Loads a BMFont .fnt file and its atlas pages, which are looked up next
+    // to it.
+    pub fn load_font(&mut self, path: &std::path::Path) -> Result<Rc<text::Font>> {
+        let src = std::fs::read_to_string(path)?;
+        let mut font = text::Font::parse(&src).map_err(|e| eyre!("{:?}: {}", path, e))?;
+        let dir = path.parent().unwrap_or_else(|| std::path::Path::new(""));
+        let pages = font
+            .page_files
+            .iter()
+            .map(|file| self.load_texture(&dir.join(file)))
+            .collect::<Result<Vec<_>>>()?;
+        font.set_pages(pages);
+        Ok(Rc::new(font))
+    }
     pub fn create_skinned_model(
         &self,
         meshes: Vec<MeshRef<skinned::Mesh>>,
@@ -35,6 +35,28 @@ pub struct SpriteRendererSettings {
 }
 #[derive(Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
 pub struct NE(pub(crate) ());
+
+// How the mouse cursor behaves over the window. A grabbed cursor can't leave
+// the window, which keeps mouse look working; a free one can go anywhere.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct CursorMode {
+    pub visible: bool,
+    pub grabbed: bool,
+}
+impl CursorMode {
+    pub const HIDDEN: Self = Self {
+        visible: false,
+        grabbed: false,
+    };
+    pub const CAPTURED: Self = Self {
+        visible: false,
+        grabbed: true,
+    };
+    pub const FREE: Self = Self {
+        visible: true,
+        grabbed: false,
+    };
+}
 impl Default for SpriteRendererSettings {
     fn default() -> Self {
         Self {
@@ -57,10 +79,13 @@ pub struct Engine {
     billboard_renderer: crate::renderer::billboard::Renderer,
     textured_renderer: crate::renderer::textured::Renderer,
     flat_renderer: crate::renderer::flat::Renderer,
+    lines_renderer: crate::renderer::lines::Renderer,
+    text_renderer: crate::renderer::text::Renderer,
     dt: f64,
     acc: f64,
     last_frame: std::time::Instant,
     moved: bool,
+    cursor: CursorMode,
 }
 
 impl Engine {
@@ -90,15 +115,19 @@ impl Engine {
         let skinned_renderer = crate::renderer::skinned::Renderer::new(&mut vulk);
         let textured_renderer = crate::renderer::textured::Renderer::new(&mut vulk);
         let flat_renderer = crate::renderer::flat::Renderer::new(&mut vulk);
+        let lines_renderer = crate::renderer::lines::Renderer::new(&mut vulk);
+        let text_renderer = crate::renderer::text::Renderer::new(&mut vulk);
         let moved = false;
         drop(vulk);
-        Self {
+        let mut engine = Self {
             assets,
             skinned_renderer,
             sprites_renderer,
             billboard_renderer,
             textured_renderer,
             flat_renderer,
+            lines_renderer,
+            text_renderer,
             vulkan,
             render_states: [
                 crate::renderer::RenderState::new(default_cam),
@@ -111,11 +140,35 @@ impl Engine {
             acc: 0.0,
             last_frame: std::time::Instant::now(),
             moved,
+            cursor: CursorMode::FREE,
+        };
+        // the window starts with the cursor hidden, as it always has
+        if let Err(e) = engine.set_cursor(CursorMode::HIDDEN) {
+            eprintln!("couldn't set the cursor: {e}");
         }
+        engine
     }
     pub fn assets(&mut self) -> &mut Assets {
         &mut self.assets
     }
+    pub fn cursor(&self) -> CursorMode {
+        self.cursor
+    }
+    // Shows or hides the cursor and grabs or releases it. Worlds ask for
+    // changes while the game runs through `World::cursor`.
+    pub fn set_cursor(&mut self, mode: CursorMode) -> Result<()> {
+        if mode == self.cursor {
+            return Ok(());
+        }
+        // remembered even if it fails, so a platform that can't grab isn't
+        // asked again every frame
+        self.cursor = mode;
+        let vulkan = self.vulkan.borrow();
+        let window = vulkan.surface.window();
+        window.set_cursor_visible(mode.visible);
+        window.set_cursor_grab(mode.grabbed)?;
+        Ok(())
+    }
     pub fn play(mut self, mut w: impl crate::World + 'static) -> Result<()> {
         let ev = self.event_loop.take().unwrap();
         self.last_frame = std::time::Instant::now();
@@ -179,6 +232,9 @@ impl Engine {
                         while self.acc >= self.dt {
                             w.update(&self.input, &mut self.assets);
                             self.input.next_frame();
+                            if let Err(e) = self.set_cursor(w.cursor()) {
+                                eprintln!("couldn't set the cursor: {e}");
+                            }
                             if self.acc <= self.dt * 2.0 {
                                 self.render_states[0].clear();
                                 w.render(&mut self.assets, &mut self.render_states[0]);
@@ -246,6 +302,15 @@ impl Engine {
             &self.assets,
             &self.interpolated_state.camera,
         );
+        self.lines_renderer
+            .prepare(&self.interpolated_state, &self.interpolated_state.camera);
+        let [w, h] = vulkan.viewport.dimensions;
+        self.text_renderer.prepare(
+            &self.interpolated_state,
+            &self.assets,
+            &self.interpolated_state.camera,
+            crate::types::Vec2::new(w, h),
+        );
 
         builder
             .begin_render_pass(
@@ -261,6 +326,8 @@ impl Engine {
         self.flat_renderer.draw(&mut builder);
         self.textured_renderer.draw(&mut builder);
         self.billboard_renderer.draw(&mut builder);
+        self.lines_renderer.draw(&mut builder);
+        self.text_renderer.draw(&mut builder);
 
         builder.end_render_pass().unwrap();
 
@@ -1,6 +1,7 @@
 pub use winit::dpi::PhysicalPosition as MousePos;
 pub use winit::event::VirtualKeyCode as Key;
-use winit::event::{ElementState, MouseButton};
+pub use winit::event::MouseButton;
+use winit::event::ElementState;
 
 pub struct Input {
     now_keys: Box<[bool]>,
@@ -4,10 +4,10 @@ pub mod animation;
 pub mod assets;
 pub mod camera;
 mod engine;
-pub use engine::{Engine, FrendererSettings, SpriteRendererSettings, WindowSettings};
+pub use engine::{CursorMode, Engine, FrendererSettings, SpriteRendererSettings, WindowSettings};
 mod image;
 mod input;
-pub use input::{Input, Key, MousePos};
+pub use input::{Input, Key, MouseButton, MousePos};
 pub mod renderer;
 pub mod types;
 mod vulkan;
@@ -15,4 +15,8 @@ mod vulkan;
 pub trait World {
     fn update(&mut self, inp: &input::Input, assets: &mut assets::Assets);
     fn render(&mut self, assets: &mut assets::Assets, render_state: &mut renderer::RenderState);
+    // What the cursor should be doing, checked after every update.
+    fn cursor(&self) -> CursorMode {
+        CursorMode::HIDDEN
+    }
 }
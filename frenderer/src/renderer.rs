@@ -1,7 +1,9 @@
 pub mod billboard;
 pub mod flat;
+pub mod lines;
 pub mod skinned;
 pub mod sprites;
+pub mod text;
 pub mod textured;
 use crate::camera::Camera;
 use crate::{assets, types::Interpolate};
@@ -74,6 +76,8 @@ pub struct RenderState {
     billboards: RenderTable<billboard::Renderer>,
     flats: RenderTable<flat::Renderer>,
     textured: RenderTable<textured::Renderer>,
+    lines: Vec<lines::Line>,
+    text: Vec<text::Text>,
     pub(crate) camera: Camera,
 }
 impl RenderState {
@@ -84,6 +88,8 @@ impl RenderState {
             billboards: RenderTable::new(),
             flats: RenderTable::new(),
             textured: RenderTable::new(),
+            lines: vec![],
+            text: vec![],
             camera: cam,
         }
     }
@@ -99,6 +105,8 @@ impl RenderState {
         self.billboards.clear();
         self.flats.clear();
         self.textured.clear();
+        self.lines.clear();
+        self.text.clear();
     }
     pub fn interpolate_from(&mut self, rs1: &Self, rs2: &Self, r: f32) {
         self.skinned.interpolate_from(&rs1.skinned, &rs2.skinned, r);
@@ -108,6 +116,9 @@ impl RenderState {
         self.flats.interpolate_from(&rs1.flats, &rs2.flats, r);
         self.textured
             .interpolate_from(&rs1.textured, &rs2.textured, r);
+        // lines and text aren't keyed, so like raw draws they just show the newest state
+        self.lines.clone_from(&rs2.lines);
+        self.text.clone_from(&rs2.text);
         self.camera = rs1.camera.interpolate_limit(rs2.camera, r, 5.0);
     }
     pub fn render_skinned(
@@ -185,4 +196,10 @@ impl RenderState {
     ) {
         self.billboards.extend_raw((tex, mode), data);
     }
+    pub fn render_lines(&mut self, lines: impl IntoIterator<Item = lines::Line>) {
+        self.lines.extend(lines);
+    }
+    pub fn render_text(&mut self, text: text::Text) {
+        self.text.push(text);
+    }
 }
@@ -0,0 +1,159 @@
+use crate::camera::Camera;
+use crate::types::*;
+use crate::vulkan::Vulkan;
+use bytemuck::{Pod, Zeroable};
+use std::sync::Arc;
+use vulkano::buffer::cpu_pool::CpuBufferPoolChunk;
+use vulkano::buffer::CpuBufferPool;
+use vulkano::command_buffer::AutoCommandBufferBuilder;
+use vulkano::descriptor_set::single_layout_pool::SingleLayoutDescSet;
+use vulkano::descriptor_set::SingleLayoutDescSetPool;
+use vulkano::pipeline::graphics::input_assembly::InputAssemblyState;
+use vulkano::pipeline::graphics::vertex_input::BuffersDefinition;
+use vulkano::pipeline::graphics::viewport::ViewportState;
+use vulkano::pipeline::GraphicsPipeline;
+use vulkano::pipeline::Pipeline;
+use vulkano::render_pass::Subpass;
+
+// A colored line segment in world space. Lines are drawn on top of everything
+// else without depth testing, which is what debug and editor overlays want.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Line {
+    pub from: Vec3,
+    pub to: Vec3,
+    pub color: Vec4,
+}
+impl Line {
+    pub fn new(from: Vec3, to: Vec3, color: Vec4) -> Self {
+        Self { from, to, color }
+    }
+}
+
+#[repr(C)]
+#[derive(Default, Debug, Clone, Copy, Pod, Zeroable)]
+pub struct Vertex {
+    pub position: [f32; 3],
+    pub color: [f32; 4],
+}
+vulkano::impl_vertex!(Vertex, position, color);
+
+pub struct Renderer {
+    pipeline: Arc<vulkano::pipeline::GraphicsPipeline>,
+    uniform_buffers: CpuBufferPool<Mat4>,
+    uniform_pds: SingleLayoutDescSetPool,
+    uniform_binding: Option<Arc<SingleLayoutDescSet>>,
+    vertex_pool: CpuBufferPool<Vertex, Arc<vulkano::memory::pool::StdMemoryPool>>,
+    verts: Vec<Vertex>,
+    vertex_buf: Option<Arc<CpuBufferPoolChunk<Vertex, Arc<vulkano::memory::pool::StdMemoryPool>>>>,
+}
+
+impl Renderer {
+    pub fn new(vulkan: &mut Vulkan) -> Self {
+        mod vs {
+            vulkano_shaders::shader! {
+                ty: "vertex",
+                src: "
+#version 450
+
+layout(location = 0) in vec3 position;
+layout(location = 1) in vec4 color;
+
+layout(set=0, binding=0) uniform BatchData { mat4 viewproj; };
+
+layout(location = 0) out vec4 out_color;
+
+void main() {
+  gl_Position = viewproj * vec4(position.xyz, 1.0);
+  out_color = color;
+}
+                "
+            }
+        }
+
+        mod fs {
+            vulkano_shaders::shader! {
+                ty: "fragment",
+                src: "
+                #version 450
+
+                layout(location = 0) in vec4 color;
+                layout(location = 0) out vec4 f_color;
+
+                void main() {
+                    f_color = color;
+                }
+            "
+            }
+        }
+
+        let vs = vs::load(vulkan.device.clone()).unwrap();
+        let fs = fs::load(vulkan.device.clone()).unwrap();
+        let pipeline = GraphicsPipeline::start()
+            .vertex_input_state(BuffersDefinition::new().vertex::<Vertex>())
+            .vertex_shader(vs.entry_point("main").unwrap(), ())
+            .input_assembly_state(InputAssemblyState::new().topology(
+                vulkano::pipeline::graphics::input_assembly::PrimitiveTopology::LineList,
+            ))
+            .viewport_state(ViewportState::viewport_dynamic_scissor_irrelevant())
+            .fragment_shader(fs.entry_point("main").unwrap(), ())
+            .render_pass(Subpass::from(vulkan.render_pass.clone(), 0).unwrap())
+            .build(vulkan.device.clone())
+            .unwrap();
+
+        let uniform_buffers = CpuBufferPool::uniform_buffer(vulkan.device.clone());
+        let uniform_pds =
+            SingleLayoutDescSetPool::new(pipeline.layout().set_layouts().get(0).unwrap().clone());
+
+        let vertex_pool = CpuBufferPool::vertex_buffer(vulkan.device.clone());
+
+        Self {
+            pipeline,
+            uniform_buffers,
+            uniform_pds,
+            uniform_binding: None,
+            vertex_pool,
+            verts: vec![],
+            vertex_buf: None,
+        }
+    }
+    pub fn prepare(&mut self, rs: &super::RenderState, camera: &Camera) {
+        self.verts.clear();
+        self.verts.extend(rs.lines.iter().flat_map(|l| {
+            [l.from, l.to].map(|p| Vertex {
+                position: [p.x, p.y, p.z],
+                color: [l.color.x, l.color.y, l.color.z, l.color.w],
+            })
+        }));
+        if self.verts.is_empty() {
+            self.vertex_buf = None;
+            return;
+        }
+        let buf = self.uniform_buffers.next(camera.as_matrix()).unwrap();
+        let uds = self
+            .uniform_pds
+            .next(vec![vulkano::descriptor_set::WriteDescriptorSet::buffer(
+                0, buf,
+            )])
+            .unwrap();
+        self.uniform_binding = Some(uds);
+        self.vertex_buf = Some(self.vertex_pool.chunk(self.verts.iter().copied()).unwrap());
+    }
+    pub fn draw<P, L>(&mut self, builder: &mut AutoCommandBufferBuilder<P, L>) {
+        let vertex_buf = match self.vertex_buf.take() {
+            Some(buf) => buf,
+            None => return,
+        };
+        let uds = self.uniform_binding.clone().unwrap();
+        builder
+            .bind_pipeline_graphics(self.pipeline.clone())
+            .bind_vertex_buffers(0, [vertex_buf])
+            .bind_descriptor_sets(
+                vulkano::pipeline::PipelineBindPoint::Graphics,
+                (*self.pipeline).layout().clone(),
+                0,
+                uds,
+            )
+            .draw(self.verts.len() as u32, 1, 0, 0)
+            .unwrap();
+    }
+}
@@ -0,0 +1,554 @@
+use crate::assets;
+use crate::camera::Camera;
+use crate::color_eyre::eyre::{ensure, eyre};
+use crate::types::*;
+use crate::vulkan::Vulkan;
+use crate::Result;
+use bytemuck::{Pod, Zeroable};
+use std::collections::HashMap;
+use std::rc::Rc;
+use std::sync::Arc;
+use vulkano::buffer::cpu_pool::CpuBufferPoolChunk;
+use vulkano::buffer::CpuBufferPool;
+use vulkano::command_buffer::AutoCommandBufferBuilder;
+use vulkano::descriptor_set::PersistentDescriptorSet;
+use vulkano::pipeline::graphics::color_blend::{
+    AttachmentBlend, BlendFactor, BlendOp, ColorBlendState,
+};
+use vulkano::pipeline::graphics::input_assembly::InputAssemblyState;
+use vulkano::pipeline::graphics::vertex_input::BuffersDefinition;
+use vulkano::pipeline::graphics::viewport::ViewportState;
+use vulkano::pipeline::GraphicsPipeline;
+use vulkano::pipeline::Pipeline;
+use vulkano::render_pass::Subpass;
+use vulkano::sampler::Sampler;
+
+// Where one character sits in a font's atlas, in atlas pixels, and how it's
+// placed relative to the pen.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Glyph {
+    pub page: usize,
+    pub region: Rect,
+    pub offset: Vec2, // from the pen to the glyph's top left, y down
+    pub advance: f32,
+}
+
+// A bitmap font in the BMFont text format: one or more atlas pages plus the
+// metrics of each glyph, all in atlas pixels.
+#[derive(Clone, Debug)]
+pub struct Font {
+    pub line_height: f32,
+    pub base: f32, // from the top of a line to the baseline
+    pub atlas_size: Vec2,
+    pub page_files: Vec<String>,
+    pages: Vec<assets::TextureRef>,
+    glyphs: HashMap<char, Glyph>,
+    kerning: HashMap<(char, char), f32>,
+}
+
+impl Font {
+    // Reads the metrics from a .fnt file's contents. The atlas pages still
+    // have to be loaded; see Assets::load_font.
+    pub fn parse(src: &str) -> Result<Self> {
+        let mut font = Font {
+            line_height: 0.0,
+            base: 0.0,
+            atlas_size: Vec2::zero(),
+            page_files: vec![],
+            pages: vec![],
+            glyphs: HashMap::new(),
+            kerning: HashMap::new(),
+        };
+        let mut saw_common = false;
+        for (i, line) in src.lines().enumerate() {
+            let (tag, fields) = bmfont_fields(line).map_err(|e| eyre!("line {}: {}", i + 1, e))?;
+            let get = |key: &str| -> Result<&str> {
+                fields
+                    .iter()
+                    .find(|(k, _)| *k == key)
+                    .map(|(_, v)| v.as_str())
+                    .ok_or_else(|| eyre!("line {}: {} is missing {}", i + 1, tag, key))
+            };
+            let num = |key: &str| -> Result<f32> {
+                let v = get(key)?;
+                v.parse()
+                    .map_err(|_| eyre!("line {}: {}={} isn't a number", i + 1, key, v))
+            };
+            match tag {
+                "common" => {
+                    font.line_height = num("lineHeight")?;
+                    font.base = num("base")?;
+                    font.atlas_size = Vec2::new(num("scaleW")?, num("scaleH")?);
+                    ensure!(
+                        font.line_height > 0.0
+                            && font.atlas_size.x > 0.0
+                            && font.atlas_size.y > 0.0,
+                        "line {}: lineHeight, scaleW and scaleH must be positive",
+                        i + 1
+                    );
+                    saw_common = true;
+                }
+                "page" => {
+                    let id = num("id")? as usize;
+                    ensure!(
+                        id == font.page_files.len(),
+                        "line {}: pages must be listed in order from 0",
+                        i + 1
+                    );
+                    font.page_files.push(get("file")?.to_string());
+                }
+                "char" => {
+                    let id = num("id")? as u32;
+                    let c = char::from_u32(id)
+                        .ok_or_else(|| eyre!("line {}: {} isn't a character", i + 1, id))?;
+                    let page = num("page")? as usize;
+                    ensure!(
+                        page < font.page_files.len(),
+                        "line {}: character {} is on page {}, which isn't listed",
+                        i + 1,
+                        id,
+                        page
+                    );
+                    font.glyphs.insert(
+                        c,
+                        Glyph {
+                            page,
+                            region: Rect::new(num("x")?, num("y")?, num("width")?, num("height")?),
+                            offset: Vec2::new(num("xoffset")?, num("yoffset")?),
+                            advance: num("xadvance")?,
+                        },
+                    );
+                }
+                "kerning" => {
+                    let first = char::from_u32(num("first")? as u32);
+                    let second = char::from_u32(num("second")? as u32);
+                    if let (Some(a), Some(b)) = (first, second) {
+                        font.kerning.insert((a, b), num("amount")?);
+                    }
+                }
+                // info, chars and kernings counts, and blank lines
+                _ => {}
+            }
+        }
+        ensure!(saw_common, "no common line");
+        ensure!(!font.page_files.is_empty(), "no pages");
+        Ok(font)
+    }
+
+    pub fn glyph(&self, c: char) -> Option<&Glyph> {
+        self.glyphs.get(&c)
+    }
+
+    // Characters the font doesn't have show as '?' if it has that.
+    fn glyph_or_fallback(&self, c: char) -> Option<&Glyph> {
+        self.glyphs.get(&c).or_else(|| self.glyphs.get(&'?'))
+    }
+
+    fn kern(&self, a: char, b: char) -> f32 {
+        self.kerning.get(&(a, b)).copied().unwrap_or(0.0)
+    }
+
+    // Width of a run of text on one line, in atlas pixels.
+    fn width(&self, text: &str) -> f32 {
+        let mut w = 0.0;
+        let mut prev = None;
+        for c in text.chars() {
+            if let Some(p) = prev {
+                w += self.kern(p, c);
+            }
+            w += self.glyph_or_fallback(c).map_or(0.0, |g| g.advance);
+            prev = Some(c);
+        }
+        w
+    }
+
+    // Breaks `text` into lines at newlines and, if the style wraps, between
+    // words. A word wider than the wrap width gets a line to itself.
+    fn lines<'a>(&self, text: &'a str, style: &TextStyle) -> Vec<&'a str> {
+        let wrap = match style.wrap {
+            Some(w) => w * self.line_height / style.size,
+            None => return text.split('\n').collect(),
+        };
+        let mut lines = vec![];
+        for para in text.split('\n') {
+            let (mut start, mut end) = (0, 0);
+            let mut word_start = 0;
+            for word in para.split(' ') {
+                let word_end = word_start + word.len();
+                if end > start && self.width(&para[start..word_end]) > wrap {
+                    lines.push(&para[start..end]);
+                    start = word_start;
+                }
+                end = word_end;
+                word_start = word_end + 1;
+            }
+            lines.push(&para[start..end]);
+        }
+        lines
+    }
+
+    // Places each glyph of `text` in layout space: x to the right, y down
+    // from the top of the first line, in the style's units. Lines are
+    // aligned around x = 0.
+    pub fn layout(&self, text: &str, style: &TextStyle) -> Vec<PlacedGlyph> {
+        let scale = style.size / self.line_height;
+        let mut placed = vec![];
+        for (row, line) in self.lines(text, style).into_iter().enumerate() {
+            let width = self.width(line);
+            let mut x = match style.align {
+                Align::Left => 0.0,
+                Align::Center => -width / 2.0,
+                Align::Right => -width,
+            };
+            let y = row as f32 * self.line_height;
+            let mut prev = None;
+            for c in line.chars() {
+                if let Some(p) = prev {
+                    x += self.kern(p, c);
+                }
+                prev = Some(c);
+                let g = match self.glyph_or_fallback(c) {
+                    Some(g) => g,
+                    None => continue,
+                };
+                if g.region.sz.x > 0.0 && g.region.sz.y > 0.0 {
+                    let min = Vec2::new(x, y) + g.offset;
+                    placed.push(PlacedGlyph {
+                        page: g.page,
+                        min: min * scale,
+                        max: (min + g.region.sz) * scale,
+                        uv: Rect {
+                            pos: g.region.pos / self.atlas_size,
+                            sz: g.region.sz / self.atlas_size,
+                        },
+                    });
+                }
+                x += g.advance;
+            }
+        }
+        placed
+    }
+
+    // Width and height of the laid out text, in the style's units.
+    pub fn measure(&self, text: &str, style: &TextStyle) -> Vec2 {
+        let lines = self.lines(text, style);
+        let width = lines.iter().map(|l| self.width(l)).fold(0.0, f32::max);
+        Vec2::new(width, lines.len() as f32 * self.line_height) * (style.size / self.line_height)
+    }
+
+    pub(crate) fn set_pages(&mut self, pages: Vec<assets::TextureRef>) {
+        self.pages = pages;
+    }
+}
+
+// Splits a BMFont line into its tag and key=value fields, unquoting values.
+fn bmfont_fields(line: &str) -> std::result::Result<(&str, Vec<(&str, String)>), String> {
+    let line = line.trim();
+    let (tag, mut rest) = line.split_once(' ').unwrap_or((line, ""));
+    let mut fields = vec![];
+    loop {
+        rest = rest.trim_start();
+        if rest.is_empty() {
+            return Ok((tag, fields));
+        }
+        let (key, after) = rest
+            .split_once('=')
+            .ok_or_else(|| format!("expected key=value at {:?}", rest))?;
+        let (value, after) = match after.strip_prefix('"') {
+            Some(quoted) => {
+                let close = quoted
+                    .find('"')
+                    .ok_or_else(|| format!("unclosed quote after {}=", key))?;
+                (&quoted[..close], &quoted[close + 1..])
+            }
+            None => after.split_once(' ').unwrap_or((after, "")),
+        };
+        fields.push((key, value.to_string()));
+        rest = after;
+    }
+}
+
+// One glyph's quad after layout, and where in the atlas it comes from.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PlacedGlyph {
+    pub page: usize,
+    pub min: Vec2,
+    pub max: Vec2,
+    pub uv: Rect, // fractions of the atlas, from the top left
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Align {
+    Left,
+    Center,
+    Right,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TextStyle {
+    pub size: f32, // line height: pixels on screen, units in the world
+    pub color: Vec4,
+    pub align: Align,
+    pub wrap: Option<f32>, // widest a line gets before breaking, in the same units
+}
+impl TextStyle {
+    pub fn new(size: f32, color: Vec4) -> Self {
+        Self {
+            size,
+            color,
+            align: Align::Left,
+            wrap: None,
+        }
+    }
+    pub fn align(mut self, align: Align) -> Self {
+        self.align = align;
+        self
+    }
+    pub fn wrap(mut self, width: f32) -> Self {
+        self.wrap = Some(width);
+        self
+    }
+}
+
+// Where a piece of text is anchored. The anchor is the top of the first line,
+// at its left, center or right edge depending on the alignment.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Space {
+    Screen(Vec2), // fraction of the window from its top left, so 0.5 is the middle
+    World(Vec3),  // facing the camera, like a billboard
+}
+
+// A string to draw this frame. Text is drawn over everything else without
+// depth testing, like lines.
+#[derive(Clone)]
+pub struct Text {
+    pub font: Rc<Font>,
+    pub text: String,
+    pub style: TextStyle,
+    pub space: Space,
+}
+impl Text {
+    pub fn new(font: Rc<Font>, text: impl Into<String>, style: TextStyle, space: Space) -> Self {
+        Self {
+            font,
+            text: text.into(),
+            style,
+            space,
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Default, Debug, Clone, Copy, Pod, Zeroable)]
+pub struct Vertex {
+    pub position: [f32; 4], // already in clip space
+    pub uv: [f32; 2],
+    pub color: [f32; 4],
+}
+vulkano::impl_vertex!(Vertex, position, uv, color);
+
+struct BatchData {
+    material_pds: Arc<PersistentDescriptorSet>,
+    verts: Vec<Vertex>,
+    vertex_buf: Option<Arc<CpuBufferPoolChunk<Vertex, Arc<vulkano::memory::pool::StdMemoryPool>>>>,
+}
+
+pub struct Renderer {
+    pipeline: Arc<GraphicsPipeline>,
+    sampler: Arc<Sampler>,
+    vertex_pool: CpuBufferPool<Vertex, Arc<vulkano::memory::pool::StdMemoryPool>>,
+    batches: HashMap<assets::TextureRef, BatchData>,
+}
+
+impl Renderer {
+    pub fn new(vulkan: &mut Vulkan) -> Self {
+        mod vs {
+            vulkano_shaders::shader! {
+                ty: "vertex",
+                src: "
+#version 450
+
+layout(location = 0) in vec4 position;
+layout(location = 1) in vec2 uv;
+layout(location = 2) in vec4 color;
+
+layout(location = 0) out vec2 out_uv;
+layout(location = 1) out vec4 out_color;
+
+void main() {
+  gl_Position = position;
+  out_uv = uv;
+  out_color = color;
+}
+"
+            }
+        }
+
+        mod fs {
+            vulkano_shaders::shader! {
+                ty: "fragment",
+                src: "
+#version 450
+
+layout(set = 0, binding = 0) uniform sampler2D tex;
+layout(location = 0) in vec2 uv;
+layout(location = 1) in vec4 color;
+layout(location = 0) out vec4 f_color;
+
+void main() {
+  // atlases are loaded with premultiplied alpha
+  f_color = texture(tex, uv) * vec4(color.rgb * color.a, color.a);
+}
+"
+            }
+        }
+
+        let vs = vs::load(vulkan.device.clone()).unwrap();
+        let fs = fs::load(vulkan.device.clone()).unwrap();
+        use vulkano::sampler::SamplerCreateInfo;
+        let sampler = Sampler::new(vulkan.device.clone(), SamplerCreateInfo::default()).unwrap();
+        let pipeline = GraphicsPipeline::start()
+            .vertex_input_state(BuffersDefinition::new().vertex::<Vertex>())
+            .vertex_shader(vs.entry_point("main").unwrap(), ())
+            .input_assembly_state(InputAssemblyState::new().topology(
+                vulkano::pipeline::graphics::input_assembly::PrimitiveTopology::TriangleList,
+            ))
+            .viewport_state(ViewportState::viewport_dynamic_scissor_irrelevant())
+            .fragment_shader(fs.entry_point("main").unwrap(), ())
+            .color_blend_state(ColorBlendState::new(1).blend(AttachmentBlend {
+                color_op: BlendOp::Add,
+                color_source: BlendFactor::One,
+                color_destination: BlendFactor::OneMinusSrcAlpha,
+                alpha_op: BlendOp::Add,
+                alpha_source: BlendFactor::One,
+                alpha_destination: BlendFactor::OneMinusSrcAlpha,
+            }))
+            .render_pass(Subpass::from(vulkan.render_pass.clone(), 0).unwrap())
+            .build(vulkan.device.clone())
+            .unwrap();
+
+        let vertex_pool = CpuBufferPool::vertex_buffer(vulkan.device.clone());
+
+        Self {
+            pipeline,
+            sampler,
+            vertex_pool,
+            batches: HashMap::new(),
+        }
+    }
+    pub fn prepare(
+        &mut self,
+        rs: &super::RenderState,
+        assets: &assets::Assets,
+        camera: &Camera,
+        screen: Vec2,
+    ) {
+        let view = camera.transform.into_homogeneous_matrix();
+        let proj = camera.projection.as_matrix(camera.ratio);
+        for text in rs.text.iter() {
+            let glyphs = text.font.layout(&text.text, &text.style);
+            // layout space to clip space
+            let to_clip: Box<dyn Fn(Vec2) -> Vec4> = match text.space {
+                Space::Screen(at) => Box::new(move |p: Vec2| {
+                    let px = at * screen + p;
+                    Vec4::new(
+                        px.x / screen.x * 2.0 - 1.0,
+                        px.y / screen.y * 2.0 - 1.0,
+                        0.5,
+                        1.0,
+                    )
+                }),
+                Space::World(at) => {
+                    let center = view * at.into_homogeneous_point();
+                    // behind the camera
+                    if center.z >= 0.0 {
+                        continue;
+                    }
+                    Box::new(move |p: Vec2| proj * (center + Vec4::new(p.x, -p.y, 0.0, 0.0)))
+                }
+            };
+            let c = text.style.color;
+            let color = [c.x, c.y, c.z, c.w];
+            for g in glyphs {
+                let tex = text.font.pages[g.page];
+                let batch = match self.batches.entry(tex) {
+                    std::collections::hash_map::Entry::Occupied(o) => o.into_mut(),
+                    std::collections::hash_map::Entry::Vacant(v) => v.insert(Self::create_batch(
+                        self.pipeline.clone(),
+                        self.sampler.clone(),
+                        assets.texture(tex),
+                    )),
+                };
+                let corner = |x: f32, y: f32| {
+                    let p = to_clip(Vec2::new(
+                        if x == 0.0 { g.min.x } else { g.max.x },
+                        if y == 0.0 { g.min.y } else { g.max.y },
+                    ));
+                    Vertex {
+                        position: [p.x, p.y, p.z, p.w],
+                        uv: [g.uv.pos.x + x * g.uv.sz.x, g.uv.pos.y + y * g.uv.sz.y],
+                        color,
+                    }
+                };
+                let (tl, bl, br, tr) = (
+                    corner(0.0, 0.0),
+                    corner(0.0, 1.0),
+                    corner(1.0, 1.0),
+                    corner(1.0, 0.0),
+                );
+                batch.verts.extend([tl, bl, br, tl, br, tr]);
+            }
+        }
+        for b in self.batches.values_mut() {
+            b.vertex_buf = if b.verts.is_empty() {
+                None
+            } else {
+                Some(self.vertex_pool.chunk(b.verts.iter().copied()).unwrap())
+            };
+        }
+    }
+    fn create_batch(
+        pipeline: Arc<GraphicsPipeline>,
+        sampler: Arc<Sampler>,
+        texture: &assets::Texture,
+    ) -> BatchData {
+        BatchData {
+            verts: vec![],
+            vertex_buf: None,
+            material_pds: PersistentDescriptorSet::new(
+                pipeline.layout().set_layouts().get(0).unwrap().clone(),
+                [
+                    vulkano::descriptor_set::WriteDescriptorSet::image_view_sampler(
+                        0,
+                        vulkano::image::view::ImageView::new_default(texture.texture.clone())
+                            .unwrap(),
+                        sampler,
+                    ),
+                ],
+            )
+            .unwrap(),
+        }
+    }
+    pub fn draw<P, L>(&mut self, builder: &mut AutoCommandBufferBuilder<P, L>) {
+        builder.bind_pipeline_graphics(self.pipeline.clone());
+        for b in self.batches.values_mut() {
+            let vertex_buf = match b.vertex_buf.take() {
+                Some(buf) => buf,
+                None => continue,
+            };
+            builder
+                .bind_vertex_buffers(0, [vertex_buf])
+                .bind_descriptor_sets(
+                    vulkano::pipeline::PipelineBindPoint::Graphics,
+                    (*self.pipeline).layout().clone(),
+                    0,
+                    b.material_pds.clone(),
+                )
+                .draw(b.verts.len() as u32, 1, 0, 0)
+                .unwrap();
+        }
+        // keep batches for fonts that are still in use, and their allocations
+        self.batches.retain(|_k, b| !b.verts.is_empty());
+        self.batches.values_mut().for_each(|b| b.verts.clear());
+    }
+}
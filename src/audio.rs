@@ -0,0 +1,177 @@
+use kira::arrangement::handle::ArrangementHandle;
+use kira::arrangement::{Arrangement, LoopArrangementSettings};
+use kira::instance::handle::InstanceHandle;
+use kira::instance::{InstanceSettings, StopInstanceSettings};
+use kira::manager::{AudioManager, AudioManagerSettings};
+use kira::parameter::tween::Tween;
+use kira::sound::handle::SoundHandle;
+use kira::sound::SoundSettings;
+use std::collections::HashMap;
+
+// seconds one level's track takes to fade into the next
+const CROSSFADE: f64 = 1.5;
+const VOLUME_STEP: f64 = 0.1;
+
+// A looping music track, loaded once and kept around for when a level wants it.
+struct Track {
+    _sound: SoundHandle,
+    arrangement: ArrangementHandle,
+}
+
+// Short sounds for things that happen in play.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Effect {
+    Jump,
+    Pickup,
+    Checkpoint,
+    Death,
+}
+
+impl Effect {
+    const ALL: [Effect; 4] = [Effect::Jump, Effect::Pickup, Effect::Checkpoint, Effect::Death];
+
+    // in content/
+    fn file(self) -> &'static str {
+        match self {
+            Effect::Jump => "jump.wav",
+            Effect::Pickup => "pickup.wav",
+            Effect::Checkpoint => "checkpoint.wav",
+            Effect::Death => "death.wav",
+        }
+    }
+}
+
+// Music and sound effects, each with its own volume from 0 to 1. Music is
+// named by file in content/, like a level's `music`.
+pub struct Audio {
+    manager: AudioManager,
+    tracks: HashMap<String, Track>,
+    effects: HashMap<Effect, SoundHandle>,
+    playing: Option<(String, InstanceHandle)>,
+    music_volume: f64,
+    effects_volume: f64,
+}
+
+impl Audio {
+    pub fn new() -> Result<Self, Box<dyn std::error::Error>> {
+        Ok(Self {
+            manager: AudioManager::new(AudioManagerSettings::default()).map_err(|e| format!("audio: {e}"))?,
+            tracks: HashMap::new(),
+            effects: HashMap::new(),
+            playing: None,
+            music_volume: 1.,
+            effects_volume: 1.,
+        })
+    }
+
+    // Loads a track ahead of time so switching to it doesn't stall a frame.
+    pub fn load_music(&mut self, file: &str) -> Result<(), Box<dyn std::error::Error>> {
+        if self.tracks.contains_key(file) {
+            return Ok(());
+        }
+        let path = format!("content/{file}");
+        let sound = self
+            .manager
+            .load_sound(&path, SoundSettings::default())
+            .map_err(|e| format!("{path}: {e}"))?;
+        let arrangement = self
+            .manager
+            .add_arrangement(Arrangement::new_loop(&sound, LoopArrangementSettings::default()))
+            .map_err(|e| format!("{path}: {e}"))?;
+        self.tracks.insert(
+            file.to_string(),
+            Track {
+                _sound: sound,
+                arrangement,
+            },
+        );
+        Ok(())
+    }
+
+    // Fades out whatever is playing and fades in `file`, or just fades out for
+    // None. Asking for the track that's already on leaves it alone.
+    pub fn play_music(&mut self, file: Option<&str>) -> Result<(), Box<dyn std::error::Error>> {
+        if self.playing.as_ref().map(|(f, _)| f.as_str()) == file {
+            return Ok(());
+        }
+        if let Some((_, mut instance)) = self.playing.take() {
+            instance
+                .stop(StopInstanceSettings::new().fade_tween(Tween::linear(CROSSFADE)))
+                .map_err(|e| format!("audio: {e}"))?;
+        }
+        let file = match file {
+            Some(file) => file,
+            None => return Ok(()),
+        };
+        self.load_music(file)?;
+        let instance = self
+            .tracks
+            .get_mut(file)
+            .unwrap()
+            .arrangement
+            .play(
+                InstanceSettings::new()
+                    .volume(self.music_volume)
+                    .fade_in_tween(Tween::linear(CROSSFADE)),
+            )
+            .map_err(|e| format!("content/{file}: {e}"))?;
+        self.playing = Some((file.to_string(), instance));
+        Ok(())
+    }
+
+    // Loads every effect up front, they're small.
+    pub fn load_effects(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        for effect in Effect::ALL {
+            let path = format!("content/{}", effect.file());
+            let sound = self
+                .manager
+                .load_sound(&path, SoundSettings::default())
+                .map_err(|e| format!("{path}: {e}"))?;
+            self.effects.insert(effect, sound);
+        }
+        Ok(())
+    }
+
+    // Plays `effect` at the effects volume, once `load_effects` has loaded it.
+    pub fn play_effect(&mut self, effect: Effect) -> Result<(), Box<dyn std::error::Error>> {
+        if let Some(sound) = self.effects.get_mut(&effect) {
+            sound
+                .play(InstanceSettings::new().volume(self.effects_volume))
+                .map_err(|e| format!("content/{}: {e}", effect.file()))?;
+        }
+        Ok(())
+    }
+
+    pub fn music_volume(&self) -> f64 {
+        self.music_volume
+    }
+
+    pub fn effects_volume(&self) -> f64 {
+        self.effects_volume
+    }
+
+    // Also changes the track that's playing right away.
+    pub fn set_music_volume(&mut self, volume: f64) -> Result<(), Box<dyn std::error::Error>> {
+        self.music_volume = volume.clamp(0., 1.);
+        if let Some((_, instance)) = self.playing.as_mut() {
+            instance
+                .set_volume(self.music_volume)
+                .map_err(|e| format!("audio: {e}"))?;
+        }
+        Ok(())
+    }
+
+    // Effects already playing keep the volume they started with.
+    pub fn set_effects_volume(&mut self, volume: f64) {
+        self.effects_volume = volume.clamp(0., 1.);
+    }
+
+    // One notch up or down, for volume keys.
+    pub fn step_music_volume(&mut self, steps: f32) -> Result<(), Box<dyn std::error::Error>> {
+        self.set_music_volume(self.music_volume + steps as f64 * VOLUME_STEP)
+    }
+
+    pub fn step_effects_volume(&mut self, steps: f32) {
+        self.set_effects_volume(self.effects_volume + steps as f64 * VOLUME_STEP)
+    }
+}
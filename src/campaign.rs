@@ -0,0 +1,158 @@
+// Where the player is in the campaign.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Progress {
+    Playing,
+    // the goal was reached and the next level hasn't started yet
+    LevelComplete,
+    // the last level's goal was reached
+    CampaignComplete,
+}
+
+// Ordered list of levels and which one is being played. Generic over the
+// level type so it doesn't need a renderer to exercise.
+pub struct Campaign<L> {
+    levels: Vec<L>,
+    current: usize,
+    progress: Progress,
+}
+
+impl<L> Campaign<L> {
+    pub fn new(levels: Vec<L>) -> Self {
+        assert!(!levels.is_empty(), "a campaign needs at least one level");
+        Self {
+            levels,
+            current: 0,
+            progress: Progress::Playing,
+        }
+    }
+
+    pub fn current(&self) -> &L {
+        &self.levels[self.current]
+    }
+
+    pub fn current_mut(&mut self) -> &mut L {
+        &mut self.levels[self.current]
+    }
+
+    pub fn level(&self, i: usize) -> Option<&L> {
+        self.levels.get(i)
+    }
+
+    pub fn index(&self) -> usize {
+        self.current
+    }
+
+    pub fn len(&self) -> usize {
+        self.levels.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.levels.is_empty()
+    }
+
+    pub fn is_last(&self) -> bool {
+        self.current + 1 == self.levels.len()
+    }
+
+    pub fn progress(&self) -> Progress {
+        self.progress
+    }
+
+    // Called when the current level's goal is reached. Does nothing unless
+    // the level is actually being played, so touching the goal for several
+    // ticks only counts once.
+    pub fn complete_level(&mut self) {
+        if self.progress != Progress::Playing {
+            return;
+        }
+        self.progress = if self.is_last() {
+            Progress::CampaignComplete
+        } else {
+            Progress::LevelComplete
+        };
+    }
+
+    // Moves on from a completed level. Returns whether a new level started;
+    // at the end of the campaign there's nowhere to go and nothing changes.
+    pub fn advance(&mut self) -> bool {
+        if self.progress != Progress::LevelComplete {
+            return false;
+        }
+        self.current += 1;
+        self.progress = Progress::Playing;
+        true
+    }
+
+    // Starts playing level `i`, whatever state the campaign was in.
+    pub fn select(&mut self, i: usize) -> bool {
+        if i >= self.levels.len() {
+            return false;
+        }
+        self.current = i;
+        self.progress = Progress::Playing;
+        true
+    }
+
+    // Plays the whole campaign again from the first level.
+    pub fn restart(&mut self) {
+        self.select(0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn campaign() -> Campaign<&'static str> {
+        Campaign::new(vec!["first", "second", "last"])
+    }
+
+    #[test]
+    fn finishing_the_last_level_ends_the_campaign() {
+        let mut c = campaign();
+        for level in ["first", "second"] {
+            assert_eq!(*c.current(), level);
+            c.complete_level();
+            assert_eq!(c.progress(), Progress::LevelComplete);
+            assert!(c.advance());
+        }
+        assert!(c.is_last());
+        c.complete_level();
+        assert_eq!(c.progress(), Progress::CampaignComplete);
+        // there's nothing after the last level, so it stays put
+        assert!(!c.advance());
+        c.complete_level();
+        assert_eq!((c.index(), c.progress()), (2, Progress::CampaignComplete));
+    }
+
+    #[test]
+    fn advancing_needs_a_completed_level() {
+        let mut c = campaign();
+        assert!(!c.advance());
+        c.complete_level();
+        // touching the goal again on later ticks doesn't skip a level
+        c.complete_level();
+        assert!(c.advance());
+        assert_eq!((c.index(), c.progress()), (1, Progress::Playing));
+    }
+
+    #[test]
+    fn selecting_out_of_range_changes_nothing() {
+        let mut c = campaign();
+        assert!(c.select(1));
+        c.complete_level();
+        assert!(!c.select(3));
+        assert_eq!((c.index(), c.progress()), (1, Progress::LevelComplete));
+        assert!(c.level(3).is_none());
+    }
+
+    #[test]
+    fn restart_goes_back_to_the_first_level() {
+        let mut c = campaign();
+        c.select(2);
+        c.complete_level();
+        c.restart();
+        assert_eq!((c.index(), c.progress()), (0, Progress::Playing));
+        assert_eq!(*c.current(), "first");
+    }
+}
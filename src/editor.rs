@@ -0,0 +1,200 @@
+use crate::physics::{BoundingBox, Collider, Colliders, Material, Shape};
+use frenderer::camera::{Camera, Projection};
+use frenderer::renderer::lines::Line;
+use frenderer::types::*;
+use frenderer::{Input, Key, MouseButton};
+
+// EDITOR SETTINGS
+const FLY_SPEED: f32 = 15.; // units/second, LShift doubles it
+const LOOK_SPEED: f32 = 0.005; // radians per unit of mouse motion
+const GRID: f32 = 0.25; // nudges and placement snap to this
+const REACH: f32 = 200.; // how far away a box can be picked
+const PLACE_DISTANCE: f32 = 6.; // new things go this far in front of the camera
+const NEW_BOX_HALF: f32 = 1.;
+const MARKER_SIZE: f32 = 0.5;
+
+const SELECTED_COLOR: Vec4 = Vec4::new(1., 1., 0., 1.);
+const PLATFORM_COLOR: Vec4 = Vec4::new(0.3, 0.5, 1., 1.);
+const START_COLOR: Vec4 = Vec4::new(0., 1., 0., 1.);
+const GOAL_COLOR: Vec4 = Vec4::new(1., 0., 1., 1.);
+const CURSOR_COLOR: Vec4 = Vec4::new(1., 1., 1., 1.);
+
+// Things the editor can't do to the level by itself.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum EditorCommand {
+    SetStart(Vec3),
+    SetGoal(Vec3),
+    Save,
+}
+
+// Free-fly camera for building levels. The crosshair is the middle of the
+// screen: clicking selects the shape under it, and new boxes, the start and
+// the goal are placed on it.
+//
+//   WASD, Q/E          fly, LShift to go faster
+//   left click         select
+//   arrows, PgUp/PgDn  move the selection along x, z and y
+//   LCtrl + the same   resize the selection instead, along its own axes
+//   N / Delete         add a box / delete the selection
+//   P / G              put the start / goal at the crosshair
+//   F5                 save
+pub struct Editor {
+    pub eye: Vec3,
+    pub yaw: f32,
+    pub pitch: f32,
+    pub selected: Option<usize>,
+    pub status: Option<String>, // shown on screen, like why a key did nothing
+}
+
+impl Editor {
+    pub fn new(eye: Vec3, yaw: f32, pitch: f32) -> Self {
+        Self {
+            eye,
+            yaw,
+            pitch,
+            selected: None,
+            status: None,
+        }
+    }
+
+    fn rotation(&self) -> Rotor3 {
+        Rotor3::from_euler_angles(0.0, self.pitch, self.yaw)
+    }
+
+    pub fn forward(&self) -> Vec3 {
+        self.rotation() * Vec3::unit_z()
+    }
+
+    pub fn camera(&self) -> Camera {
+        Camera::look_at(
+            self.eye,
+            self.eye + self.forward(),
+            Vec3::unit_y(),
+            Projection::Perspective { fov: PI / 2.0 },
+        )
+    }
+
+    // Where the crosshair meets the level, or a spot in front of the camera
+    // when it's pointing at nothing close.
+    pub fn cursor(&self, colliders: &Colliders) -> Vec3 {
+        let reach = self.forward() * PLACE_DISTANCE * 2.;
+        match colliders.ray_cast(self.eye, reach) {
+            Some(hit) => hit.point,
+            None => self.eye + self.forward() * PLACE_DISTANCE,
+        }
+    }
+
+    pub fn update(&mut self, input: &Input, dt: f32, colliders: &mut Colliders) -> Option<EditorCommand> {
+        // FLY
+        let (dx, dy) = input.get_delta();
+        self.yaw += dx as f32 * LOOK_SPEED;
+        self.pitch = (self.pitch + dy as f32 * LOOK_SPEED).clamp(-PI / 2.1, PI / 2.1);
+        let speed = if input.is_key_down(Key::LShift) { FLY_SPEED * 2. } else { FLY_SPEED };
+        let fly = Vec3::new(
+            input.key_axis(Key::D, Key::A),
+            input.key_axis(Key::Q, Key::E),
+            input.key_axis(Key::S, Key::W),
+        );
+        self.eye += self.rotation() * fly * speed * dt;
+
+        // SELECT
+        if input.is_mouse_pressed(MouseButton::Left) {
+            self.selected = match colliders.ray_cast(self.eye, self.forward() * REACH) {
+                Some(hit) => match hit.collider {
+                    Collider::Level(i) => Some(i),
+                    Collider::Platform(_) => None,
+                },
+                None => None,
+            };
+        }
+
+        // ADD AND DELETE
+        if input.is_key_pressed(Key::N) {
+            let at = snap(self.eye + self.forward() * PLACE_DISTANCE);
+            colliders
+                .shapes
+                .push(Shape::Aabb(BoundingBox::around(at, Vec3::broadcast(NEW_BOX_HALF))));
+            colliders.materials.push(Material::default());
+            colliders.rebuild();
+            self.selected = Some(colliders.shapes.len() - 1);
+        }
+        if let Some(i) = self.selected {
+            if input.is_key_pressed(Key::Delete) {
+                colliders.shapes.remove(i);
+                colliders.materials.remove(i);
+                colliders.rebuild();
+                self.selected = None;
+            }
+        }
+
+        // MOVE AND RESIZE
+        if let Some(i) = self.selected {
+            let nudge = Vec3::new(
+                pressed_axis(input, Key::Right, Key::Left),
+                pressed_axis(input, Key::PageUp, Key::PageDown),
+                pressed_axis(input, Key::Up, Key::Down),
+            ) * GRID;
+            if nudge != Vec3::zero() {
+                let shape = &mut colliders.shapes[i];
+                if input.is_key_down(Key::LControl) {
+                    if !shape.resize(nudge, GRID / 2.) {
+                        self.status = Some("triangles from the level's mesh can't be resized".to_string());
+                    }
+                } else {
+                    shape.translate(nudge);
+                }
+                colliders.rebuild();
+            }
+        }
+
+        // LEVEL MARKERS AND SAVING
+        if input.is_key_pressed(Key::P) {
+            return Some(EditorCommand::SetStart(snap(self.cursor(colliders)) + Vec3::unit_y()));
+        }
+        if input.is_key_pressed(Key::G) {
+            return Some(EditorCommand::SetGoal(snap(self.cursor(colliders)) + Vec3::unit_y()));
+        }
+        if input.is_key_pressed(Key::F5) {
+            return Some(EditorCommand::Save);
+        }
+        None
+    }
+
+    // Wireframes for every collision shape plus the start, goals and crosshair.
+    pub fn lines(&self, colliders: &Colliders, start: Vec3, goals: &[Vec3]) -> Vec<Line> {
+        let mut lines = vec![];
+        for (i, (shape, material)) in colliders.shapes.iter().zip(colliders.materials.iter()).enumerate() {
+            let color = if self.selected == Some(i) { SELECTED_COLOR } else { material.color() };
+            lines.extend(shape.edges().into_iter().map(|(from, to)| Line::new(from, to, color)));
+        }
+        for shape in colliders.platforms.iter() {
+            lines.extend(shape.edges().into_iter().map(|(from, to)| Line::new(from, to, PLATFORM_COLOR)));
+        }
+        lines.extend(marker(start, START_COLOR));
+        for &goal in goals {
+            lines.extend(marker(goal, GOAL_COLOR));
+        }
+        lines.extend(marker(self.cursor(colliders), CURSOR_COLOR));
+        lines
+    }
+}
+
+fn snap(p: Vec3) -> Vec3 {
+    (p / GRID).map(f32::round) * GRID
+}
+
+// like key_axis, but only on the tick a key goes down
+pub fn pressed_axis(input: &Input, plus: Key, minus: Key) -> f32 {
+    match (input.is_key_pressed(plus), input.is_key_pressed(minus)) {
+        (true, false) => 1.,
+        (false, true) => -1.,
+        _ => 0.,
+    }
+}
+
+// three short crossed lines
+fn marker(at: Vec3, color: Vec4) -> impl Iterator<Item = Line> {
+    [Vec3::unit_x(), Vec3::unit_y(), Vec3::unit_z()]
+        .into_iter()
+        .map(move |axis| Line::new(at - axis * MARKER_SIZE, at + axis * MARKER_SIZE, color))
+}
@@ -0,0 +1,18 @@
+use std::fs::File;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+// Writes `contents` to a temporary file next to `path` and renames it over
+// the old file, so a crash partway through leaves the old file as it was.
+pub fn write_atomically(path: &Path, contents: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".tmp");
+    let tmp = PathBuf::from(tmp);
+    let write = || -> std::io::Result<()> {
+        let mut file = File::create(&tmp)?;
+        file.write_all(contents.as_bytes())?;
+        file.sync_all()?;
+        std::fs::rename(&tmp, path)
+    };
+    write().map_err(|e| format!("{}: {e}", path.display()).into())
+}
@@ -0,0 +1,240 @@
+use crate::physics::BoundingBox;
+use crate::validate::JumpPhysics;
+use frenderer::types::*;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::fmt::Write;
+
+// GENERATOR SETTINGS
+const START_HEIGHT: f32 = 10.; // top of the first platform
+const MIN_HEIGHT: f32 = 4.; // no platform top goes below this
+const PLATFORM_HALF_HEIGHT: f32 = 0.5;
+const CLIMB_MARGIN: f32 = 0.6; // fraction of the highest possible double jump a climb may use
+const GAP_MARGIN: f32 = 0.8; // fraction of the longest possible jump a gap may use
+const JITTER: f32 = 0.5; // sideways wobble, as a fraction of the narrower platform
+const DEATH_MARGIN: f32 = 5.; // how far below the lowest platform falling kills
+const PLACEMENT_TRIES: usize = 8; // headings tried before going straight
+
+// How hard a generated level is.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Difficulty {
+    pub platforms: usize,
+    // edge to edge, shrunk whenever the jump couldn't make it
+    pub min_gap: f32,
+    pub max_gap: f32,
+    // largest climb or drop from one platform to the next
+    pub height_variance: f32,
+    // half widths of platforms
+    pub min_size: f32,
+    pub max_size: f32,
+}
+
+impl Difficulty {
+    pub const EASY: Self = Self {
+        platforms: 8,
+        min_gap: 2.,
+        max_gap: 5.,
+        height_variance: 2.,
+        min_size: 3.,
+        max_size: 5.,
+    };
+    pub const NORMAL: Self = Self {
+        platforms: 12,
+        min_gap: 4.,
+        max_gap: 9.,
+        height_variance: 4.,
+        min_size: 2.,
+        max_size: 4.,
+    };
+    pub const HARD: Self = Self {
+        platforms: 16,
+        min_gap: 7.,
+        max_gap: 14.,
+        height_variance: 7.,
+        min_size: 1.25,
+        max_size: 2.5,
+    };
+
+    pub fn named(name: &str) -> Option<Self> {
+        match name {
+            "easy" => Some(Self::EASY),
+            "normal" => Some(Self::NORMAL),
+            "hard" => Some(Self::HARD),
+            _ => None,
+        }
+    }
+}
+
+// A run of platforms from the spawn to the goal.
+#[derive(Clone, Debug, PartialEq)]
+pub struct GeneratedLevel {
+    pub seed: u64,
+    pub boxes: Vec<BoundingBox>,
+    pub spawn: Vec3,
+    pub goal: Vec3,
+    pub death_height: f32,
+}
+
+// Lays out `difficulty.platforms` boxes from `seed`, each one a jump away
+// from the last with `physics`. The same seed always gives the same level.
+// If a run boxes itself in so that not even going straight is clear, it ends
+// early rather than put one platform inside another.
+pub fn generate(seed: u64, difficulty: &Difficulty, physics: &JumpPhysics) -> GeneratedLevel {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let start_half = Vec3::new(difficulty.max_size, PLATFORM_HALF_HEIGHT, difficulty.max_size);
+    let mut boxes = vec![BoundingBox::around(
+        Vec3::new(0., START_HEIGHT - PLATFORM_HALF_HEIGHT, 0.),
+        start_half,
+    )];
+    // the way the run is heading: +z, or sideways along +x or -x
+    let mut heading = Vec3::unit_z();
+
+    for _ in 1..difficulty.platforms {
+        let prev = *boxes.last().unwrap();
+        let prev_half = (prev.max() - prev.min()) / 2.;
+        let half = Vec3::new(
+            rng.gen_range(difficulty.min_size..=difficulty.max_size),
+            PLATFORM_HALF_HEIGHT,
+            rng.gen_range(difficulty.min_size..=difficulty.max_size),
+        );
+        let top = prev.max().y;
+        let dy = rng
+            .gen_range(-difficulty.height_variance..=difficulty.height_variance)
+            .min(physics.max_rise() * CLIMB_MARGIN)
+            .max(MIN_HEIGHT - top);
+        let reach = physics.air_time(dy).unwrap_or(0.) * physics.top_speed * GAP_MARGIN;
+        let gap = rng.gen_range(difficulty.min_gap..=difficulty.max_gap).min(reach);
+
+        // keep clear of everything but the platform it's jumped to from,
+        // including the air above it where the jump arcs
+        let clear = |candidate: &BoundingBox| {
+            let above = BoundingBox::around(candidate.center() + Vec3::unit_y() * physics.max_rise(), half);
+            let clearance = candidate.union(&above);
+            !boxes[..boxes.len() - 1].iter().any(|b| b.overlaps(&clearance))
+        };
+        let mut placed = None;
+        for _ in 0..PLACEMENT_TRIES {
+            // never double straight back on the last heading
+            let options: Vec<Vec3> = [Vec3::unit_z(), Vec3::unit_x(), -Vec3::unit_x()]
+                .into_iter()
+                .filter(|&d| d != -heading)
+                .collect();
+            let d = options[rng.gen_range(0..options.len())];
+            let side = Vec3::new(d.z.abs(), 0., d.x.abs());
+            let along = prev_half.dot(d.abs()) + gap + half.dot(d.abs());
+            let wobble = prev_half.dot(side).min(half.dot(side)) * JITTER;
+            let mut center = prev.center() + d * along + side * rng.gen_range(-wobble..=wobble);
+            center.y = top + dy - half.y;
+            let candidate = BoundingBox::around(center, half);
+            if clear(&candidate) {
+                placed = Some((d, candidate));
+                break;
+            }
+        }
+        if placed.is_none() {
+            let along = prev_half.z + gap + half.z;
+            let center = Vec3::new(prev.center().x, top + dy - half.y, prev.center().z + along);
+            let candidate = BoundingBox::around(center, half);
+            placed = clear(&candidate).then_some((Vec3::unit_z(), candidate));
+        }
+        let Some((d, placed)) = placed else {
+            break;
+        };
+        heading = d;
+        boxes.push(placed);
+    }
+
+    let first = boxes[0];
+    let last = *boxes.last().unwrap();
+    let lowest = boxes.iter().map(|b| b.min().y).fold(f32::INFINITY, f32::min);
+    GeneratedLevel {
+        seed,
+        spawn: Vec3::new(first.center().x, first.max().y + physics.radius + 0.5, first.center().z),
+        goal: Vec3::new(last.center().x, last.max().y + physics.pickup_half.y + 0.5, last.center().z),
+        death_height: lowest - DEATH_MARGIN,
+        boxes,
+    }
+}
+
+impl GeneratedLevel {
+    // The boxes as a Wavefront OBJ, each face mapped to the whole texture.
+    pub fn to_obj(&self) -> String {
+        let mut obj = format!("# jumpyball level, seed {}\n", self.seed);
+        obj.push_str("vt 0 0\nvt 1 0\nvt 1 1\nvt 0 1\n");
+        let mut count = 0;
+        for b in self.boxes.iter() {
+            let (center, half) = (b.center(), (b.max() - b.min()) / 2.);
+            for axis in 0..3 {
+                let (u, v) = ((axis + 1) % 3, (axis + 2) % 3);
+                for sign in [-1., 1.] {
+                    let mut corners = [(-1., -1.), (1., -1.), (1., 1.), (-1., 1.)];
+                    // counterclockwise seen from outside
+                    if sign < 0. {
+                        corners.reverse();
+                    }
+                    for (su, sv) in corners {
+                        let mut p = center;
+                        p[axis] += sign * half[axis];
+                        p[u] += su * half[u];
+                        p[v] += sv * half[v];
+                        writeln!(obj, "v {} {} {}", p.x, p.y, p.z).unwrap();
+                    }
+                    writeln!(
+                        obj,
+                        "f {}/1 {}/2 {}/3 {}/4",
+                        count + 1,
+                        count + 2,
+                        count + 3,
+                        count + 4
+                    )
+                    .unwrap();
+                    count += 4;
+                }
+            }
+        }
+        obj
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::physics::{Colliders, Shape};
+    use crate::validate::check_level;
+
+    // the game's own settings
+    const PHYSICS: JumpPhysics = JumpPhysics {
+        radius: 1.,
+        top_speed: 12.,
+        jump_speed: 36.,
+        gravity: -108.,
+        jumps: 2,
+        pickup_half: Vec3::new(0.5, 1., 0.5),
+    };
+
+    #[test]
+    fn same_seed_same_level() {
+        for d in [Difficulty::EASY, Difficulty::NORMAL, Difficulty::HARD] {
+            assert_eq!(generate(7, &d, &PHYSICS), generate(7, &d, &PHYSICS));
+            assert_ne!(generate(7, &d, &PHYSICS), generate(8, &d, &PHYSICS));
+        }
+    }
+
+    #[test]
+    fn generated_levels_pass_the_level_check() {
+        for d in [Difficulty::EASY, Difficulty::NORMAL, Difficulty::HARD] {
+            for seed in 0..200 {
+                let level = generate(seed, &d, &PHYSICS);
+                let colliders = Colliders::new(level.boxes.iter().map(|&b| Shape::Aabb(b)).collect());
+                let problems = check_level(&PHYSICS, &colliders, &[], level.spawn, &[level.goal], &[]);
+                assert_eq!(problems, vec![], "seed {seed}, {d:?}");
+                for (i, a) in level.boxes.iter().enumerate() {
+                    assert!(
+                        !level.boxes[i + 1..].iter().any(|b| a.overlaps(b)),
+                        "seed {seed}, {d:?}: box {i} overlaps a later one"
+                    );
+                }
+            }
+        }
+    }
+}
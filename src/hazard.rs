@@ -0,0 +1,47 @@
+use crate::physics::{BoundingBox, Sphere};
+use frenderer::types::*;
+use serde::Deserialize;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+pub enum HazardKind {
+    Lava,
+    Spikes,
+    OutOfBounds,
+}
+
+// Box that kills the player on touch.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Hazard {
+    pub kind: HazardKind,
+    pub volume: BoundingBox,
+}
+
+impl Hazard {
+    pub fn touching(&self, ball: &Sphere) -> bool {
+        ball.touching(&self.volume)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DeathCause {
+    Fell, // dropped below the level's death height
+    Hazard(HazardKind),
+}
+
+// Sent whenever the player dies, before they're respawned.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Death {
+    pub cause: DeathCause,
+    pub position: Vec3,
+}
+
+// Why the ball at `ball` dies this tick, if it does.
+pub fn check_death(ball: &Sphere, death_height: f32, hazards: &[Hazard]) -> Option<DeathCause> {
+    if ball.pos.y < death_height {
+        return Some(DeathCause::Fell);
+    }
+    hazards
+        .iter()
+        .find(|h| h.touching(ball))
+        .map(|h| DeathCause::Hazard(h.kind))
+}
@@ -0,0 +1,17 @@
+pub mod audio;
+pub mod campaign;
+pub mod editor;
+pub mod files;
+pub mod generate;
+pub mod hazard;
+pub mod manifest;
+pub mod menu;
+pub mod movement;
+pub mod parse;
+pub mod physics;
+pub mod platform;
+pub mod records;
+pub mod save;
+pub mod speedrun;
+pub mod state;
+pub mod validate;
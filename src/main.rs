@@ -1,158 +1,380 @@
 #![allow(dead_code)]
 use frenderer::camera::{Camera, Projection};
+use frenderer::renderer::text::{Align, Font, Space, Text, TextStyle};
 use frenderer::renderer::textured::SingleRenderState as FTextured;
 use frenderer::types::*;
-use std::fs::File;
-use std::io::{BufRead, BufReader};
-use frenderer::{Engine, Key, Result, FrendererSettings, SpriteRendererSettings};
+use frenderer::{CursorMode, Engine, Key, Result, FrendererSettings, SpriteRendererSettings};
+use std::path::PathBuf;
 use std::rc::Rc;
-use kira::arrangement::{Arrangement, LoopArrangementSettings};
-use kira::instance::InstanceSettings;
-use kira::manager::{AudioManager, AudioManagerSettings};
-use kira::sound::SoundSettings;
+
+use jumpyball::audio::{Audio, Effect};
+use jumpyball::physics::{self, BoundingBox, Collider, Colliders, ContactInfo, Material, Shape, Sphere};
+use jumpyball::campaign::{Campaign, Progress};
+use jumpyball::editor::{pressed_axis, Editor, EditorCommand};
+use jumpyball::generate::{generate, Difficulty};
+use jumpyball::hazard::{check_death, Death, Hazard};
+use jumpyball::menu::{MenuInput, PauseAction, PauseMenu};
+use jumpyball::manifest::{CollisionSpec, HazardSpec, LevelSpec, Manifest};
+use jumpyball::movement::Movement;
+use jumpyball::platform::{Platform, PlatformSpec};
+use jumpyball::save::{self, SaveData, Settings};
+use jumpyball::speedrun::{format_delta, format_time, RunTimer};
+use jumpyball::state::{apply_to_run, GameState, StateEvent};
+use jumpyball::validate::{check_level, JumpPhysics};
 
 // GAME SETTINGS
 const DT: f64 = 1.0 / 60.0; // time steps
 const PR: f32 = 1.; // player radius
-const PV: f32 = 0.2; // player velocity
-const GR: f32 = -0.03; // acceleration from gravity
+const PV: f32 = 12.; // top rolling speed in units/second
+const PA: f32 = 30.; // rolling acceleration in units/second^2
+const AC: f32 = 0.4; // fraction of PA available in the air
+const FR: f32 = 12.; // ground friction deceleration in units/second^2
+const DG: f32 = 0.5; // drag, fraction of speed lost per second
+const JV: f32 = 36.; // jump velocity in units/second
+const GR: f32 = -108.; // acceleration from gravity in units/second^2
+const CT: f32 = 0.1; // coyote time, seconds after leaving a ledge that still count as grounded
+const JB: f32 = 0.12; // jump buffer, seconds a press is remembered before landing
+const JC: f32 = 0.5; // fraction of upward speed kept when Space is released mid-jump
+const MJ: u8 = 2; // jumps before landing again, 2 is a double jump
+const PH: Vec3 = Vec3::new(0.5, 1., 0.5); // half size of the box around goals and collectibles
+// the settings above as the level validator and generator see them
+const JUMP: JumpPhysics = JumpPhysics {
+    radius: PR,
+    top_speed: PV,
+    jump_speed: JV,
+    gravity: GR,
+    jumps: MJ,
+    pickup_half: PH,
+};
+// and as the ball's movement sees them
+const MOVEMENT: Movement = Movement {
+    top_speed: PV,
+    accel: PA,
+    air_control: AC,
+    friction: FR,
+    drag: DG,
+    gravity: GR,
+};
 const CS: f64 = 5.; // camera sense
 
+// render keys, each kind of thing gets its own range
+const GOAL_KEYS: usize = 1000;
+const PLATFORM_KEYS: usize = 2000;
+const CHECKPOINT_KEYS: usize = 3000;
+const COLLECTIBLE_KEYS: usize = 4000;
+
+// seconds the ball sits at the goal before the next level starts
+const LEVEL_COMPLETE_TIME: f32 = 1.5;
+// radians/second the camera circles the level behind the title and credits
+const MENU_CAMERA_SPIN: f32 = 0.2;
+const MENU_CAMERA_PITCH: f32 = 0.5;
+const MENU_CAMERA_DISTANCE: f32 = 25.;
+// checkpoint marker size when inactive, and spin in radians/second when active
+const CHECKPOINT_IDLE_SCALE: f32 = 0.5;
+const CHECKPOINT_SPIN: f32 = 3.;
+// collectibles reuse the goal gem, just smaller; locked goals shrink too
+const COLLECTIBLE_SCALE: f32 = 0.4;
+const LOCKED_GOAL_SCALE: f32 = 0.5;
+// goals and collectibles bob this far above and below where they sit, taking
+// this many seconds to go down and back up
+const BOB_HEIGHT: f32 = 0.25;
+const BOB_PERIOD: f32 = 10. / 3.;
+const MANIFEST_FILE: &str = "content/levels.ron";
+// a random run is this many generated levels, seeded one after another
+const RANDOM_RUN_LEVELS: u64 = 5;
+const RANDOM_RUN_TEXTURE: &str = "level_1.png";
+const RANDOM_RUN_MUSIC: &str = "jumpyball.ogg";
+// text sizes are line heights in pixels, except labels in the world
+const FONT_FILE: &str = "content/dejavu_sans_32.fnt";
+const HUD_TEXT: f32 = 24.;
+const MENU_TEXT: f32 = 36.;
+const HEADING_TEXT: f32 = 72.;
+const GOAL_LABEL_TEXT: f32 = 0.6; // units
+const TEXT_COLOR: Vec4 = Vec4::new(1., 1., 1., 1.);
+const HINT_TEXT_COLOR: Vec4 = Vec4::new(0.7, 0.7, 0.7, 1.);
+const SELECTED_TEXT_COLOR: Vec4 = Vec4::new(1., 0.85, 0.2, 1.);
+// run time against the best run
+const AHEAD_TEXT_COLOR: Vec4 = Vec4::new(1., 0.8, 0.1, 1.);
+const BEHIND_TEXT_COLOR: Vec4 = Vec4::new(1., 0.3, 0.25, 1.);
+// number keys jump straight to a level
+const LEVEL_SELECT_KEYS: [Key; 9] = [
+    Key::Key1, Key::Key2, Key::Key3, Key::Key4, Key::Key5, Key::Key6, Key::Key7, Key::Key8, Key::Key9,
+];
+
 fn new_level(
     engine: &mut Engine,
-    level_name: &str,
+    spec: &LevelSpec,
     goal_model: Rc<frenderer::renderer::textured::Model>,
-    start: Vec3, 
-    end: Vec3
 ) -> Result<Level, Box<dyn std::error::Error>> {
+    let name = &spec.name;
+    // generated levels live outside content/ and use absolute paths
+    let content = |file: &str| std::path::Path::new("content").join(file).to_string_lossy().into_owned();
 
-    let level_tex = engine.assets().load_texture(std::path::Path::new(&format!("content/{level_name}.png")))?;
-    let level_mesh = engine.assets().load_textured(std::path::Path::new(&format!("content/{level_name}.obj")))?;
+    let level_tex = engine
+        .assets()
+        .load_texture(std::path::Path::new(&content(&spec.texture)))
+        .map_err(|e| format!("level {name:?}: texture {}: {e}", spec.texture))?;
+    let level_mesh = engine
+        .assets()
+        .load_textured(std::path::Path::new(&content(&spec.mesh)))
+        .map_err(|e| format!("level {name:?}: mesh {}: {e}", spec.mesh))?;
 
     let l = level_mesh.len();
 
-    let level_model = engine.assets().create_textured_model(level_mesh, vec![level_tex; l]);
+    let trf = Similarity3::new(Vec3::zero(), Rotor3::identity(), 1.);
+
+    let collision_file = match &spec.collision {
+        CollisionSpec::Boxes(file) => Some(content(file)),
+        CollisionSpec::Mesh => None,
+    };
+    let (shapes, materials): (Vec<Shape>, Vec<Material>) = match &spec.collision {
+        CollisionSpec::Boxes(file) => Shape::from_file(&content(file))
+            .map_err(|e| format!("level {name:?}: collision: {e}"))?
+            .into_iter()
+            .unzip(),
+        CollisionSpec::Mesh => level_mesh
+            .iter()
+            .flat_map(|m| {
+                let mesh = &engine.assets().textured_mesh(*m).mesh;
+                let verts: Vec<Vec3> = mesh
+                    .vertices
+                    .iter()
+                    .map(|v| trf.transform_vec(Vec3::new(v.x, v.y, v.z)))
+                    .collect();
+                let indices: Vec<u32> = mesh.faces.iter().flat_map(|f| f.0.iter().copied()).collect();
+                Shape::triangles(&verts, &indices)
+            })
+            .map(|t| (t, Material::default()))
+            .unzip(),
+    };
+    let mut colliders = Colliders::with_materials(shapes, materials);
+
+    let platforms = match &spec.platforms {
+        Some(file) => Platform::from_file(engine.assets(), &content(file))
+            .map_err(|e| format!("level {name:?}: platforms: {e}"))?,
+        None => vec![],
+    };
+    colliders.platforms = platforms.iter().map(Platform::shape).collect();
 
-    let bounding_boxes = BoundingBox::from_file(&format!("content/{level_name}_bb.txt")).unwrap();
+    let goal_model = match &spec.goal_model {
+        Some(m) => {
+            let tex = engine
+                .assets()
+                .load_texture(std::path::Path::new(&content(&m.texture)))
+                .map_err(|e| format!("level {name:?}: goal texture {}: {e}", m.texture))?;
+            let mesh = engine
+                .assets()
+                .load_textured(std::path::Path::new(&content(&m.mesh)))
+                .map_err(|e| format!("level {name:?}: goal mesh {}: {e}", m.mesh))?;
+            let l = mesh.len();
+            engine.assets().create_textured_model(mesh, vec![tex; l])
+        }
+        None => goal_model,
+    };
+
+    let level_model = engine.assets().create_textured_model(level_mesh, vec![level_tex; l]);
 
     let level: Level = Level {
-        trf: Similarity3::new(Vec3::zero(), Rotor3::identity(), 1.),
+        name: name.clone(),
+        collision_file,
+        trf,
         model: level_model,
-        bounding_boxes,
-        start,
-        goal: Goal {
-            trf: Similarity3::new(end, Rotor3::identity(), 1.),
-            model: goal_model,
-            anim_counter: 50
-        },
+        colliders,
+        platforms,
+        start: spec.spawn(),
+        goals: spec
+            .goals()
+            .map(|end| Goal {
+                trf: Similarity3::new(end, Rotor3::identity(), 1.),
+                home: end,
+                model: goal_model.clone(),
+                // a quarter of the way in, it's level with where it sits
+                bob_time: BOB_PERIOD / 4.,
+            })
+            .collect(),
+        collectibles: spec
+            .collectibles()
+            .enumerate()
+            .map(|(i, at)| Collectible {
+                gem: Goal {
+                    trf: Similarity3::new(at, Rotor3::identity(), COLLECTIBLE_SCALE),
+                    home: at,
+                    model: goal_model.clone(),
+                    // out of step with each other so a row of them ripples
+                    bob_time: (i as f32 * 0.6) % BOB_PERIOD,
+                },
+                taken: false,
+            })
+            .collect(),
+        required: spec.required,
+        hazards: spec.hazards.iter().map(HazardSpec::hazard).collect(),
+        checkpoints: spec
+            .checkpoints
+            .iter()
+            .map(|c| Checkpoint {
+                volume: BoundingBox::around(c.at(), c.half()),
+                trf: Similarity3::new(c.at(), Rotor3::identity(), CHECKPOINT_IDLE_SCALE),
+                model: goal_model.clone(),
+            })
+            .collect(),
+        active_checkpoint: None,
+        music: spec.music.clone(),
+        death_height: spec.death_height,
     };
 
     Ok(level)
 }
 
-struct BoundingBox {
-    min_x: f32,
-    max_x: f32,
-    min_y: f32,
-    max_y: f32,
-    min_z: f32,
-    max_z: f32,
-}
+// Writes what the editor can change back to the level's shape file and its
+// entry in the manifest.
+fn save_level(level: &Level) -> Result<(), Box<dyn std::error::Error>> {
+    let file = level
+        .collision_file
+        .as_ref()
+        .ok_or("its collision comes from its mesh, there are no boxes to save")?;
+    let shapes: Vec<(Shape, Material)> = level
+        .colliders
+        .shapes
+        .iter()
+        .copied()
+        .zip(level.colliders.materials.iter().copied())
+        .collect();
+    Shape::to_file(file, &shapes)?;
 
-impl BoundingBox {
-    fn new(min_x: f32, max_x: f32, min_y: f32, max_y: f32, min_z: f32, max_z: f32) -> Self {
-        Self {
-            min_x,
-            max_x,
-            min_y,
-            max_y,
-            min_z,
-            max_z
-        }
-    }
-
-    fn from_file(filepath: &str) -> Result<Vec<Self>, Box<dyn std::error::Error>> {
-        let file = File::open(filepath)?;
-        let reader = BufReader::new(file);
-
-        let mut boxes: Vec<Self> = vec![];
-
-        for line in reader.lines() {
-            let unwrap = line.unwrap();
-            let split: Vec<&str> = unwrap.split(' ').collect::<Vec<&str>>();
-            let cast: Vec<f32> = split.iter().map(|s| s.parse::<f32>().unwrap()).collect();
-            boxes.push(Self {
-                min_x: cast[0],
-                max_x: cast[1],
-                min_y: cast[2],
-                max_y: cast[3],
-                min_z: cast[4],
-                max_z: cast[5],
-            })
-        }
+    let goals: Vec<Vec3> = level.goals.iter().map(|g| g.home).collect();
+    Manifest::save_spawn_and_goals(MANIFEST_FILE, &level.name, level.start, &goals)
+}
 
-        Ok(boxes)
-    }
+// Every level in the manifest, in the order listed.
+fn load_levels(
+    engine: &mut Engine,
+    manifest_path: &str,
+    goal_model: Rc<frenderer::renderer::textured::Model>,
+) -> Result<Vec<Level>, Box<dyn std::error::Error>> {
+    let manifest = Manifest::from_file(manifest_path)?;
+    manifest
+        .levels
+        .iter()
+        .map(|spec| new_level(engine, spec, goal_model.clone()).map_err(|e| format!("{manifest_path}: {e}").into()))
+        .collect()
 }
 
-struct Sphere {
-    pos: Vec3,
-    r: f32,
+// Builds a random run starting from `seed`. Meshes only load from files, so
+// each level's mesh goes through one scratch file that's removed as soon as
+// the run is loaded; collision is the generated boxes themselves.
+fn random_levels(
+    engine: &mut Engine,
+    seed: u64,
+    difficulty: &Difficulty,
+    goal_model: Rc<frenderer::renderer::textured::Model>,
+) -> Result<Vec<Level>, Box<dyn std::error::Error>> {
+    let dir = std::env::temp_dir().join(format!("jumpyball_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).map_err(|e| format!("{}: {e}", dir.display()))?;
+    let mesh = dir.join("random.obj").to_string_lossy().into_owned();
+    let levels = (0..RANDOM_RUN_LEVELS)
+        .map(|i| {
+            let generated = generate(seed.wrapping_add(i), difficulty, &JUMP);
+            std::fs::write(&mesh, generated.to_obj()).map_err(|e| format!("{mesh}: {e}"))?;
+            let spec = LevelSpec {
+                name: format!("random {}", generated.seed),
+                mesh: mesh.clone(),
+                texture: RANDOM_RUN_TEXTURE.to_string(),
+                collision: CollisionSpec::Mesh,
+                spawn: (generated.spawn.x, generated.spawn.y, generated.spawn.z),
+                goals: vec![(generated.goal.x, generated.goal.y, generated.goal.z)],
+                collectibles: vec![],
+                required: 0,
+                checkpoints: vec![],
+                hazards: vec![],
+                goal_model: None,
+                music: Some(RANDOM_RUN_MUSIC.to_string()),
+                death_height: generated.death_height,
+                platforms: None,
+            };
+            let mut level = new_level(engine, &spec, goal_model.clone())?;
+            // the boxes exactly, rather than the triangles of their mesh
+            level.colliders = Colliders::new(generated.boxes.iter().map(|&b| Shape::Aabb(b)).collect());
+            Ok(level)
+        })
+        .collect();
+    std::fs::remove_dir_all(&dir).map_err(|e| format!("{}: {e}", dir.display()))?;
+    levels
 }
 
-fn player_touching_end(p:&Player, g:&Goal) -> bool {
-    let s: Sphere = Sphere { 
-        pos: p.trf.translation,
-        r: PR,
+// `--random [seed]` and `--difficulty easy|normal|hard` ask for a random run
+// instead of the campaign. Without a seed one is picked from the clock.
+fn random_run_args(args: &[String]) -> Result<Option<(u64, Difficulty)>, Box<dyn std::error::Error>> {
+    let i = match args.iter().position(|a| a == "--random") {
+        Some(i) => i,
+        None => return Ok(None),
     };
-
-    let b: BoundingBox = BoundingBox { 
-        min_x: g.trf.translation.x - 0.5,
-        max_x: g.trf.translation.x + 0.5,
-        min_y: g.trf.translation.y - 1.,
-        max_y: g.trf.translation.y + 1.,
-        min_z: g.trf.translation.z - 0.5,
-        max_z: g.trf.translation.z + 0.5,
+    let seed = match args.get(i + 1).filter(|a| !a.starts_with("--")) {
+        Some(seed) => seed.parse::<u64>().map_err(|e| format!("--random {seed}: {e}"))?,
+        None => std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH)?.as_secs(),
     };
+    let difficulty = match args.iter().position(|a| a == "--difficulty") {
+        Some(j) => {
+            let name = args.get(j + 1).map(String::as_str).unwrap_or("");
+            Difficulty::named(name).ok_or_else(|| format!("--difficulty {name:?}: expected easy, normal or hard"))?
+        }
+        None => Difficulty::NORMAL,
+    };
+    Ok(Some((seed, difficulty)))
+}
 
-    let closest = Vec3::new(
-        s.pos.x.clamp(b.min_x, b.max_x),
-        s.pos.y.clamp(b.min_y, b.max_y),
-        s.pos.z.clamp(b.min_z, b.max_z),
-    );
-
-    let dist = closest - s.pos;
+// Checks every level in the manifest for a buried spawn or goal and for
+// things the player can't jump to, printing what it finds.
+fn validate_levels(manifest_path: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let manifest = Manifest::from_file(manifest_path)?;
+    let content = |file: &str| format!("content/{file}");
+    let mut bad_levels = 0;
+    for spec in manifest.levels.iter() {
+        let name = &spec.name;
+        let file = match &spec.collision {
+            CollisionSpec::Boxes(file) => content(file),
+            CollisionSpec::Mesh => {
+                println!("{name}: skipped, its collision comes from its mesh");
+                continue;
+            }
+        };
+        let (shapes, materials) = Shape::from_file(&file)?.into_iter().unzip();
+        let mut colliders = Colliders::with_materials(shapes, materials);
+        let platforms = match &spec.platforms {
+            Some(file) => PlatformSpec::from_file(&content(file))?,
+            None => vec![],
+        };
+        colliders.platforms = platforms
+            .iter()
+            .map(|p| Shape::Aabb(BoundingBox::around(p.path[0], p.half)))
+            .collect();
 
-    dist.mag() <= s.r
+        let goals: Vec<Vec3> = spec.goals().collect();
+        let collectibles: Vec<Vec3> = spec.collectibles().collect();
+        let problems = check_level(&JUMP, &colliders, &platforms, spec.spawn(), &goals, &collectibles);
+        if problems.is_empty() {
+            println!("{name}: ok");
+        } else {
+            bad_levels += 1;
+            for problem in problems {
+                println!("{name}: {problem}");
+            }
+        }
+    }
+    match bad_levels {
+        0 => Ok(()),
+        n => Err(format!("{manifest_path}: {n} level(s) have problems").into()),
+    }
 }
 
-fn handle_collision(p: &mut Player, b: &BoundingBox) {
+fn player_touching_end(p:&Player, g:&Goal) -> bool {
     let s: Sphere = Sphere { 
         pos: p.trf.translation,
         r: PR,
     };
 
-    let closest = Vec3::new(
-        s.pos.x.clamp(b.min_x, b.max_x),
-        s.pos.y.clamp(b.min_y, b.max_y),
-        s.pos.z.clamp(b.min_z, b.max_z),
-    );
+    let b: BoundingBox = BoundingBox::around(g.trf.translation, PH);
 
-    let dist = closest - s.pos;
-
-    if dist.mag() < s.r {
-        let rest = (s.pos - closest).normalized() * (s.r-(s.pos - closest).mag());
-
-        if rest.x.abs() > rest.y.abs() && rest.x.abs() > rest.z.abs() {
-            p.trf.translation.x += rest.x;
-        } else if rest.y.abs() > rest.z.abs() {
-            p.trf.translation.y += rest.y;
-            p.vy = 0.;
-            p.jump_count = 0;
-        } else {
-            p.trf.translation.z += rest.z;
-        }
-    }
+    s.touching(&b)
 }
 
 pub struct OrbitCamera {
@@ -204,30 +426,116 @@ impl OrbitCamera {
 struct Player {
     trf: Similarity3,
     model: Rc<frenderer::renderer::textured::Model>,
-    vy: f32,
+    vel: Vec3,
     jump_count: u8,
+    air_time: f32,    // seconds since last on the ground
+    jump_buffer: f32, // seconds a jump press is still waiting to be used
+    jumping: bool,    // rising from a jump, so releasing Space cuts it short
+    contacts: ContactInfo,
 }
 
 struct Level {
+    name: String,
+    collision_file: Option<String>, // None when collision comes from the mesh
     trf: Similarity3,
     model: Rc<frenderer::renderer::textured::Model>,
-    bounding_boxes: Vec<BoundingBox>,
+    colliders: Colliders,
+    platforms: Vec<Platform>,
     start: Vec3,
-    goal: Goal,
+    goals: Vec<Goal>,
+    collectibles: Vec<Collectible>,
+    required: usize, // collectibles needed before the goals open
+    hazards: Vec<Hazard>,
+    checkpoints: Vec<Checkpoint>,
+    active_checkpoint: Option<usize>,
+    music: Option<String>,
+    death_height: f32,
+}
+
+impl Level {
+    // Puts moving parts back where they were when the level loaded and
+    // forgets any checkpoint.
+    fn reset(&mut self) {
+        for (i, platform) in self.platforms.iter_mut().enumerate() {
+            platform.reset();
+            self.colliders.platforms[i] = platform.shape();
+        }
+        self.active_checkpoint = None;
+        for c in self.collectibles.iter_mut() {
+            c.taken = false;
+        }
+        for checkpoint in self.checkpoints.iter_mut() {
+            checkpoint.trf.scale = CHECKPOINT_IDLE_SCALE;
+            checkpoint.trf.rotation = Rotor3::identity();
+        }
+    }
+
+    fn collected(&self) -> usize {
+        self.collectibles.iter().filter(|c| c.taken).count()
+    }
+
+    fn goals_open(&self) -> bool {
+        self.collected() >= self.required
+    }
+
+    // where the player comes back after falling
+    fn respawn_point(&self) -> Vec3 {
+        match self.active_checkpoint {
+            Some(i) => self.checkpoints[i].trf.translation,
+            None => self.start,
+        }
+    }
+}
+
+// Marker drawn with the goal model: small and still until touched, then
+// full size and spinning while it's the respawn point.
+struct Checkpoint {
+    volume: BoundingBox,
+    trf: Similarity3,
+    model: Rc<frenderer::renderer::textured::Model>,
 }
 
 struct Goal {
     trf: Similarity3,
+    home: Vec3, // where it bobs around
     model: Rc<frenderer::renderer::textured::Model>,
-    anim_counter: u16
+    bob_time: f32, // seconds into the current bob
+}
+
+impl Goal {
+    // slow bob up and down, `dt` seconds further along
+    fn bob(&mut self, dt: f32) {
+        self.bob_time = (self.bob_time + dt) % BOB_PERIOD;
+        let phase = self.bob_time / BOB_PERIOD;
+        // down for the first half of the period, then back up
+        self.trf.translation.y = self.home.y + BOB_HEIGHT * (4. * (phase - 0.5).abs() - 1.);
+    }
+}
+
+// A small gem to pick up on the way, it stays gone until the level restarts.
+struct Collectible {
+    gem: Goal,
+    taken: bool,
 }
 struct World {
     camera: Camera,
     camera_control: OrbitCamera,
     player: Player,
-    levels: Vec<Level>,
-    level_i: usize,
-    level: Level
+    campaign: Campaign<Level>,
+    complete_timer: f32, // seconds left before the next level starts
+    deaths: Vec<Death>,  // deaths this tick, handed to on_death at the end of it
+    death_count: u32,
+    last_death: Option<Death>,
+    timer: RunTimer,
+    save: SaveData,
+    save_path: Option<PathBuf>, // None when progress isn't written back
+    notice: Option<String>,     // shown on the title screen, like a save that couldn't be read
+    seed: Option<u64>,          // of a random run, shown so a good one can be played again
+    editor: Option<Editor>,
+    audio: Audio,
+    state: GameState,
+    pause_menu: PauseMenu,
+    font: Rc<Font>,
 }
 struct Flat {
     trf: Similarity3,
@@ -236,108 +544,578 @@ struct Flat {
 
 impl frenderer::World for World {
     fn update(&mut self, input: &frenderer::Input, _assets: &mut frenderer::assets::Assets) {
+        let dt = DT as f32;
+
+        let volumes = (self.audio.music_volume(), self.audio.effects_volume());
+
+        // VOLUME
+        if let Err(e) = self.audio.step_music_volume(pressed_axis(input, Key::Equals, Key::Minus)) {
+            eprintln!("couldn't change the music volume: {e}");
+        }
+        self.audio.step_effects_volume(pressed_axis(input, Key::RBracket, Key::LBracket));
+
+        match self.state {
+            GameState::Title => self.update_title(input, dt),
+            GameState::Playing => self.update_playing(input, dt),
+            GameState::Paused => self.update_paused(input),
+            GameState::LevelComplete => self.update_level_complete(input, dt),
+            GameState::Credits => self.update_credits(input, dt),
+        }
+
+        if (self.audio.music_volume(), self.audio.effects_volume()) != volumes {
+            save_progress(self);
+        }
+    }
+
+    fn render(
+        &mut self,
+        _a: &mut frenderer::assets::Assets,
+        rs: &mut frenderer::renderer::RenderState,
+    ) {
+        rs.set_camera(self.camera);
+        self.render_level(rs);
+        if self.state.shows_player() {
+            rs.render_textured(0, self.player.model.clone(), FTextured::new(self.player.trf));
+        }
+        if let (GameState::Playing, Some(editor)) = (self.state, &self.editor) {
+            let level = self.campaign.current();
+            let goals: Vec<Vec3> = level.goals.iter().map(|g| g.home).collect();
+            rs.render_lines(editor.lines(&level.colliders, level.start, &goals));
+        }
+        match self.state {
+            GameState::Title => self.render_title(rs),
+            GameState::Playing => self.render_hud(rs),
+            GameState::Paused => self.render_pause_menu(rs),
+            GameState::LevelComplete => self.render_level_summary(rs),
+            GameState::Credits => self.render_credits(rs),
+        }
+    }
+
+    // The mouse steers the camera while playing, so it's kept in the window
+    // then and let go everywhere else.
+    fn cursor(&self) -> CursorMode {
+        match self.state {
+            GameState::Playing | GameState::LevelComplete => CursorMode::CAPTURED,
+            GameState::Title | GameState::Paused | GameState::Credits => CursorMode::FREE,
+        }
+    }
+}
+
+impl World {
+    // The current level turning slowly behind the title.
+    fn update_title(&mut self, input: &frenderer::Input, dt: f32) {
+        for (i, key) in LEVEL_SELECT_KEYS.iter().enumerate() {
+            if input.is_key_pressed(*key) && is_unlocked(self, i) {
+                start_level(self, i);
+            }
+        }
+        if input.is_key_pressed(Key::Return) || input.is_key_pressed(Key::Space) {
+            change_state(self, StateEvent::Start);
+        }
+        self.spin_camera(dt);
+    }
+
+    fn update_playing(&mut self, input: &frenderer::Input, dt: f32) {
+        if input.is_key_pressed(Key::Escape) && self.editor.is_none() {
+            change_state(self, StateEvent::Pause);
+            return;
+        }
+        self.timer.tick();
+
+        // EDITOR
+        if input.is_key_pressed(Key::Tab) {
+            self.editor = match self.editor {
+                Some(_) => {
+                    // shapes may have been deleted out from under these
+                    self.player.contacts = ContactInfo::default();
+                    None
+                }
+                None => {
+                    let c = &self.camera_control;
+                    let rot = Rotor3::from_euler_angles(0.0, c.pitch, c.yaw);
+                    let eye = self.player.trf.translation + rot * Vec3::new(0.0, 0.0, -c.distance);
+                    Some(Editor::new(eye, c.yaw, c.pitch))
+                }
+            };
+        }
+        if let Some(editor) = self.editor.as_mut() {
+            self.timer.disqualify();
+            let level = self.campaign.current_mut();
+            match editor.update(input, dt, &mut level.colliders) {
+                Some(EditorCommand::SetStart(at)) => level.start = at,
+                Some(EditorCommand::SetGoal(at)) => {
+                    if let Some(goal) = level.goals.first_mut() {
+                        goal.home = at;
+                        goal.trf.translation = at;
+                    }
+                }
+                Some(EditorCommand::Save) => {
+                    editor.status = Some(match save_level(level) {
+                        Ok(()) => format!("saved level {:?}", level.name),
+                        Err(e) => format!("couldn't save level {:?}: {e}", level.name),
+                    });
+                }
+                None => {}
+            }
+            self.camera = editor.camera();
+            return;
+        }
+
+        // LEVEL SELECT
+        for (i, key) in LEVEL_SELECT_KEYS.iter().enumerate() {
+            if input.is_key_pressed(*key) && is_unlocked(self, i) {
+                start_level(self, i);
+                // jumping around starts a new run from there
+                self.timer.start(i);
+            }
+        }
+        let level = self.campaign.current_mut();
+
+        // MOVE PLATFORMS
+        for (i, platform) in level.platforms.iter_mut().enumerate() {
+            let delta = platform.step(dt);
+            let collider = Collider::Platform(i);
+            let riding = self.player.contacts.ground().map(|c| c.collider) == Some(collider);
+            if riding {
+                self.player.trf.translation += delta;
+            }
+            level.colliders.platforms[i] = platform.shape();
+            physics::push_out(&mut self.player.trf.translation, PR, collider, &platform.shape());
+        }
+
         // JUMP MECHANICS
-        if input.is_key_pressed(Key::Space) && self.player.jump_count < 2 {
-            self.player.vy = 3. * PV;
+        if input.is_key_pressed(Key::Space) {
+            self.player.jump_buffer = JB;
+        }
+        // past the grace period, rolling off a ledge costs the ground jump
+        if self.player.air_time > CT && self.player.jump_count == 0 {
+            self.player.jump_count = 1;
+        }
+        if self.player.jump_buffer > 0. && self.player.jump_count < MJ {
+            self.player.vel.y = JV;
             self.player.jump_count += 1;
+            self.player.jump_buffer = 0.;
+            self.player.jumping = true;
+            play_effect(&mut self.audio, Effect::Jump);
+        }
+        self.player.jump_buffer -= dt;
+        if self.player.jumping && input.is_key_released(Key::Space) && self.player.vel.y > 0. {
+            self.player.vel.y *= JC;
+            self.player.jumping = false;
         }
-        
+
         // CALCULATE PLAYER MOVEMENT
         let rotation = Rotor3::from_euler_angles(0.0, 0.0, self.camera_control.yaw);
-        self.player.vy += GR;
-        let move_vec = rotation * Vec3::new(
-            input.key_axis(Key::D, Key::A),
-            self.player.vy,
-            input.key_axis(Key::S, Key::W)
-        );
-        
-        // EXECUTE PLAYER MOVEMENT
-        self.player.trf.translation.x += PV * move_vec.x;
-        self.player.trf.translation.y += move_vec.y;
-        self.player.trf.translation.z += PV * move_vec.z;
-      
-        // GROUND CHECK
-        if self.player.trf.translation.y < PR {
-            // self.player.trf.translation.y = 1.;
-            self.player.trf.translation = self.level.start;
-            self.player.vy = 0.;
-            self.player.jump_count = 0;
+        let mut wish = rotation * Vec3::new(input.key_axis(Key::D, Key::A), 0., input.key_axis(Key::S, Key::W));
+        if wish.mag_sq() > 1. {
+            wish.normalize();
         }
+        let ground = self.player.contacts.ground().map(|c| level.colliders.material(c.collider));
+        self.player.vel = MOVEMENT.roll(self.player.vel, wish, ground, dt);
+        self.player.vel = MOVEMENT.before_move(self.player.vel, dt);
 
-        // ADJUST ROTATION BASED ON JUMP
-        let rot_mult = if self.player.jump_count == 0 {
-            1.
-        } else if self.player.jump_count == 1 {
-            0.5
+        // EXECUTE PLAYER MOVEMENT
+        let before = self.player.trf.translation;
+        self.player.contacts = physics::move_sphere(
+            &mut self.player.trf.translation,
+            &mut self.player.vel,
+            PR,
+            dt,
+            &level.colliders
+        );
+        self.player.vel = MOVEMENT.after_move(self.player.vel, dt);
+        if self.player.contacts.grounded() {
+            self.player.jump_count = 0;
+            self.player.air_time = 0.;
         } else {
-            2.
-        };
+            self.player.air_time += dt;
+        }
+        if self.player.vel.y <= 0. {
+            self.player.jumping = false;
+        }
+        let moved = self.player.trf.translation - before;
       
-        // HANDLE COLLISION
-        for b in &self.level.bounding_boxes {
-            handle_collision(&mut self.player, b);
+        // DEATH CHECK
+        let ball = Sphere { pos: self.player.trf.translation, r: PR };
+        if let Some(cause) = check_death(&ball, level.death_height, &level.hazards) {
+            self.deaths.push(Death { cause, position: ball.pos });
+            respawn_player(&mut self.player, level.respawn_point());
         }
 
-        // CHECK END OF LEVEL
-        if player_touching_end(&self.player, &self.level.goal) {
-            next_level(self);
+        // CHECKPOINTS
+        let ball = Sphere { pos: self.player.trf.translation, r: PR };
+        for (i, checkpoint) in level.checkpoints.iter_mut().enumerate() {
+            if ball.touching(&checkpoint.volume) && level.active_checkpoint != Some(i) {
+                level.active_checkpoint = Some(i);
+                play_effect(&mut self.audio, Effect::Checkpoint);
+            }
+            let active = level.active_checkpoint == Some(i);
+            checkpoint.trf.scale = if active { 1. } else { CHECKPOINT_IDLE_SCALE };
+            if active {
+                checkpoint.trf.prepend_rotation(Rotor3::from_rotation_xz(CHECKPOINT_SPIN * dt));
+            } else {
+                checkpoint.trf.rotation = Rotor3::identity();
+            }
         }
 
         // ROTATE PLAYER
-        self.player.trf.prepend_rotation(Rotor3 {
-            s: 1.,
-            bv: Bivec3 {
-                xy: (move_vec.x / (2.*PI*PR)) * rot_mult,
-                xz: 0.,
-                yz: -(move_vec.z / (2.*PI*PR)) * rot_mult,
-            },
-        });
+        // a ball rolling without slipping turns by distance / radius about the
+        // horizontal axis perpendicular to where it went
+        let roll = Vec3::new(moved.x, 0., moved.z);
+        if roll.mag_sq() > 0. {
+            let axis = Vec3::unit_y().cross(roll).normalized();
+            self.player.trf.prepend_rotation(Rotor3::from_angle_plane(
+                roll.mag() / PR,
+                Bivec3::from_normalized_axis(axis),
+            ));
+            self.player.trf.rotation.normalize();
+        }
 
         // ADJUST CAMERA
         self.camera_control.update(input, &self.player);
         self.camera_control.update_camera(&mut self.camera);
 
         // ANIMATE GOAL
-        if self.level.goal.anim_counter >= 200 { self.level.goal.anim_counter = 0 }
-        let dy: f32 = if self.level.goal.anim_counter < 100 { -0.005 } else { 0.005 };
-        self.level.goal.anim_counter += 1;
-        self.level.goal.trf.translation.y += dy;
+        // locked goals sit shrunk until enough collectibles are in
+        let goal_scale = if level.goals_open() { 1. } else { LOCKED_GOAL_SCALE };
+        for goal in level.goals.iter_mut() {
+            goal.bob(dt);
+            goal.trf.scale = goal_scale;
+        }
+
+        // COLLECTIBLES
+        for c in level.collectibles.iter_mut().filter(|c| !c.taken) {
+            c.gem.bob(dt);
+            if player_touching_end(&self.player, &c.gem) {
+                c.taken = true;
+                play_effect(&mut self.audio, Effect::Pickup);
+            }
+        }
+
+        // CHECK END OF LEVEL
+        let finished = level.goals_open() && level.goals.iter().any(|g| player_touching_end(&self.player, g));
+        if finished {
+            self.save.records.submit(&level.name, level.collected());
+            self.campaign.complete_level();
+            // beating a level opens the next one up for level select
+            if let Some(next) = self.campaign.level(self.campaign.index() + 1) {
+                self.save.unlock(&next.name);
+            }
+            save_progress(self);
+            change_state(self, StateEvent::LevelFinished);
+        }
+
+        // DEATH EVENTS
+        for death in std::mem::take(&mut self.deaths) {
+            on_death(self, death);
+        }
     }
 
-    fn render(
-        &mut self,
-        _a: &mut frenderer::assets::Assets,
-        rs: &mut frenderer::renderer::RenderState,
-    ) {
-        rs.set_camera(self.camera);
+    // Everything stands still, the camera included, while the menu is up.
+    fn update_paused(&mut self, input: &frenderer::Input) {
+        match MenuInput::from_keys(input).and_then(|m| self.pause_menu.handle(m)) {
+            Some(PauseAction::Resume) => change_state(self, StateEvent::Resume),
+            Some(PauseAction::RestartLevel) => change_state(self, StateEvent::RestartLevel),
+            Some(PauseAction::Quit) => change_state(self, StateEvent::QuitToTitle),
+            Some(PauseAction::MusicVolume(notches)) => {
+                if let Err(e) = self.audio.step_music_volume(notches as f32) {
+                    eprintln!("couldn't change the music volume: {e}");
+                }
+            }
+            Some(PauseAction::EffectsVolume(notches)) => self.audio.step_effects_volume(notches as f32),
+            None => {}
+        }
+    }
+
+    // The ball waits at the goal while the camera can still look around, until
+    // the summary times out or Return skips it.
+    fn update_level_complete(&mut self, input: &frenderer::Input, dt: f32) {
+        self.complete_timer -= dt;
+        if self.complete_timer <= 0. || input.is_key_pressed(Key::Return) {
+            let event = match self.campaign.progress() {
+                Progress::CampaignComplete => StateEvent::CampaignFinished,
+                _ => StateEvent::NextLevel,
+            };
+            change_state(self, event);
+            return;
+        }
+        self.camera_control.update(input, &self.player);
+        self.camera_control.update_camera(&mut self.camera);
+    }
+
+    fn update_credits(&mut self, input: &frenderer::Input, dt: f32) {
+        if input.is_key_pressed(Key::Return) {
+            change_state(self, StateEvent::BackToTitle);
+        }
+        self.spin_camera(dt);
+    }
+
+    fn spin_camera(&mut self, dt: f32) {
+        self.camera_control.yaw += MENU_CAMERA_SPIN * dt;
+        let rot = Rotor3::from_euler_angles(0.0, MENU_CAMERA_PITCH, self.camera_control.yaw);
+        let at = self.campaign.current().start;
+        self.camera = Camera::look_at(
+            at + rot * Vec3::new(0.0, 0.0, -MENU_CAMERA_DISTANCE),
+            at,
+            Vec3::unit_y(),
+            Projection::Perspective { fov: PI / 2.0 },
+        );
+    }
+
+    // `text` anchored at `at`, a fraction of the window from the top left
+    fn screen_text(&self, rs: &mut frenderer::renderer::RenderState, text: &str, style: TextStyle, at: Vec2) {
+        rs.render_text(Text::new(self.font.clone(), text, style, Space::Screen(at)));
+    }
+
+    fn render_title(&self, rs: &mut frenderer::renderer::RenderState) {
+        let centered = |size, color| TextStyle::new(size, color).align(Align::Center);
+        self.screen_text(rs, "jumpyball", centered(HEADING_TEXT, TEXT_COLOR), Vec2::new(0.5, 0.25));
+        let mut hint = match self.seed {
+            Some(seed) => format!("Random run, seed {seed}\nPress Enter to play"),
+            None => "Press Enter to play".to_string(),
+        };
+        let levels = (0..LEVEL_SELECT_KEYS.len()).take_while(|&i| is_unlocked(self, i)).count();
+        if levels > 1 {
+            hint += &format!("\n1-{levels} picks a level");
+        }
+        self.screen_text(rs, &hint, centered(MENU_TEXT, HINT_TEXT_COLOR), Vec2::new(0.5, 0.6));
+        if let Some(notice) = &self.notice {
+            let style = centered(HUD_TEXT, BEHIND_TEXT_COLOR).wrap(HUD_TEXT * 40.);
+            self.screen_text(rs, notice, style, Vec2::new(0.5, 0.8));
+        }
+    }
 
-        rs.render_textured(0, self.player.model.clone(), FTextured::new(self.player.trf));
-        rs.render_textured(1, self.level.model.clone(), FTextured::new(self.level.trf));
-        rs.render_textured(2, self.level.goal.model.clone(), FTextured::new(self.level.goal.trf));
+    fn render_hud(&self, rs: &mut frenderer::renderer::RenderState) {
+        let level = self.campaign.current();
+        let mut hud = format!("{}\nDeaths {}", level.name, self.death_count);
+        if !level.collectibles.is_empty() {
+            hud += &format!("\nGems {}/{}", level.collected(), level.collectibles.len());
+        }
+        self.screen_text(rs, &hud, TextStyle::new(HUD_TEXT, TEXT_COLOR), Vec2::new(0.02, 0.02));
+        self.render_timer(rs);
+        if let Some(status) = self.editor.as_ref().and_then(|e| e.status.as_deref()) {
+            self.screen_text(rs, status, TextStyle::new(HUD_TEXT, HINT_TEXT_COLOR), Vec2::new(0.02, 0.95));
+        }
+        // what a locked goal is waiting for, floating over it
+        if !level.goals_open() {
+            let left = level.required - level.collected();
+            let label = format!("{left} more gem{}", if left == 1 { "" } else { "s" });
+            for goal in level.goals.iter() {
+                rs.render_text(Text::new(
+                    self.font.clone(),
+                    label.as_str(),
+                    TextStyle::new(GOAL_LABEL_TEXT, TEXT_COLOR).align(Align::Center),
+                    Space::World(goal.home + Vec3::unit_y() * 2.),
+                ));
+            }
+        }
     }
-} 
 
-fn next_level(world: &mut World) {
-    world.level = world.levels.pop().unwrap();
-    world.player.trf.translation = world.level.start;
+    // Run time in the top right with the level's time under it, each with how
+    // far ahead or behind the bests it is.
+    fn render_timer(&self, rs: &mut frenderer::renderer::RenderState) {
+        let level = self.campaign.current();
+        let style = |color| TextStyle::new(HUD_TEXT, color).align(Align::Right);
+        let at = Vec2::new(0.98, 0.02);
+        let delta_color = |delta: i64| if delta <= 0 { AHEAD_TEXT_COLOR } else { BEHIND_TEXT_COLOR };
+        let times = format!(
+            "{}\n\nLevel {}",
+            format_time(self.timer.ticks(), DT),
+            format_time(self.timer.level_ticks(), DT)
+        );
+        self.screen_text(rs, &times, style(TEXT_COLOR), at);
+        // each delta goes on the line under its time
+        if let Some(delta) = self.timer.delta(&self.save.bests.run) {
+            self.screen_text(rs, &format!("\n{}", format_delta(delta, DT)), style(delta_color(delta)), at);
+        }
+        if let (true, Some(best)) = (self.timer.level_counts(), self.save.bests.level(&level.name)) {
+            let delta = self.timer.level_ticks() as i64 - best as i64;
+            // nothing to say about the level until it's gone past the best
+            if delta > 0 {
+                self.screen_text(rs, &format!("\n\n\n{}", format_delta(delta, DT)), style(BEHIND_TEXT_COLOR), at);
+            }
+        }
+    }
+
+    fn render_pause_menu(&self, rs: &mut frenderer::renderer::RenderState) {
+        let centered = |size, color| TextStyle::new(size, color).align(Align::Center);
+        let heading = if self.pause_menu.in_settings() { "Settings" } else { "Paused" };
+        self.screen_text(rs, heading, centered(HEADING_TEXT, TEXT_COLOR), Vec2::new(0.5, 0.2));
+        let labels = self.pause_menu.labels(self.audio.music_volume(), self.audio.effects_volume());
+        let selected = self.pause_menu.selected();
+        // the selected item is drawn on its own, over a blank line in the rest
+        let others: Vec<&str> = labels
+            .iter()
+            .enumerate()
+            .map(|(i, l)| if i == selected { "" } else { l.as_str() })
+            .collect();
+        let at = Vec2::new(0.5, 0.45);
+        self.screen_text(rs, &others.join("\n"), centered(MENU_TEXT, HINT_TEXT_COLOR), at);
+        let highlighted = "\n".repeat(selected) + &labels[selected];
+        self.screen_text(rs, &highlighted, centered(MENU_TEXT, SELECTED_TEXT_COLOR), at);
+    }
+
+    fn render_level_summary(&self, rs: &mut frenderer::renderer::RenderState) {
+        let level = self.campaign.current();
+        let centered = |size, color| TextStyle::new(size, color).align(Align::Center);
+        self.screen_text(rs, "Level complete!", centered(HEADING_TEXT, TEXT_COLOR), Vec2::new(0.5, 0.25));
+        let ticks = self.timer.level_ticks();
+        let mut summary = format!("Time {}", format_time(ticks, DT));
+        match self.save.bests.level(&level.name) {
+            _ if !self.timer.level_counts() => {}
+            Some(best) if best <= ticks => summary += &format!(", best {}", format_time(best, DT)),
+            _ => summary += ", a new best!",
+        }
+        summary += &format!("\nDeaths {}", self.death_count);
+        if !level.collectibles.is_empty() {
+            summary += &format!(
+                "\nGems {}/{}, best {}",
+                level.collected(),
+                level.collectibles.len(),
+                self.save.records.best(&level.name)
+            );
+        }
+        self.screen_text(rs, &summary, centered(MENU_TEXT, TEXT_COLOR), Vec2::new(0.5, 0.45));
+    }
+
+    fn render_credits(&self, rs: &mut frenderer::renderer::RenderState) {
+        let centered = |size, color| TextStyle::new(size, color).align(Align::Center);
+        self.screen_text(rs, "Thanks for playing!", centered(HEADING_TEXT, TEXT_COLOR), Vec2::new(0.5, 0.2));
+        self.screen_text(
+            rs,
+            &format!(
+                "You made it through all {} levels in {} with {} deaths.\n\nText is set in DejaVu Sans.\n\nPress Enter to go back to the title",
+                self.campaign.len(),
+                format_time(self.timer.ticks(), DT),
+                self.death_count
+            ),
+            centered(MENU_TEXT, HINT_TEXT_COLOR).wrap(MENU_TEXT * 20.),
+            Vec2::new(0.5, 0.4),
+        );
+    }
+
+    fn render_level(&self, rs: &mut frenderer::renderer::RenderState) {
+        let level = self.campaign.current();
+        rs.render_textured(1, level.model.clone(), FTextured::new(level.trf));
+        for (i, goal) in level.goals.iter().enumerate() {
+            rs.render_textured(GOAL_KEYS + i, goal.model.clone(), FTextured::new(goal.trf));
+        }
+        for (i, platform) in level.platforms.iter().enumerate() {
+            rs.render_textured(PLATFORM_KEYS + i, platform.model.clone(), FTextured::new(platform.trf));
+        }
+        for (i, checkpoint) in level.checkpoints.iter().enumerate() {
+            rs.render_textured(CHECKPOINT_KEYS + i, checkpoint.model.clone(), FTextured::new(checkpoint.trf));
+        }
+        for (i, c) in level.collectibles.iter().enumerate().filter(|(_, c)| !c.taken) {
+            rs.render_textured(COLLECTIBLE_KEYS + i, c.gem.model.clone(), FTextured::new(c.gem.trf));
+        }
+    }
 }
 
-fn main() -> Result<()> {
-    frenderer::color_eyre::install()?;
-    let mut audio_manager = AudioManager::new(AudioManagerSettings::default()).unwrap();
+// Moves to wherever `event` leads from the current state and does what
+// getting there involves. Events that don't apply to the current state are
+// ignored.
+fn change_state(world: &mut World, event: StateEvent) {
+    let next = match world.state.on(event) {
+        Some(next) => next,
+        None => return,
+    };
+    match event {
+        StateEvent::Start => world.notice = None,
+        StateEvent::LevelFinished => world.complete_timer = LEVEL_COMPLETE_TIME,
+        _ => {}
+    }
+    let changes = apply_to_run(
+        event,
+        &mut world.campaign,
+        &mut world.timer,
+        &mut world.save.bests,
+        |l| l.name.as_str(),
+    );
+    if changes.improved {
+        save_progress(world);
+    }
+    if changes.respawn {
+        spawn_player(world);
+    }
+    world.state = next;
+}
 
-    let mut sound_handle_music = audio_manager
-        .load_sound("content/jumpyball.ogg", SoundSettings::default())
-        .unwrap();
+// Writes everything worth keeping to the save file, when there is one. A lost
+// save isn't worth stopping the game over.
+fn save_progress(world: &mut World) {
+    world.save.settings = Settings {
+        music_volume: world.audio.music_volume(),
+        effects_volume: world.audio.effects_volume(),
+    };
+    if let Some(path) = &world.save_path {
+        if let Err(e) = world.save.save(path) {
+            eprintln!("couldn't save progress: {e}");
+        }
+    }
+}
 
-    let mut arrangement_handle = audio_manager
-        .add_arrangement(Arrangement::new_loop(
-            &sound_handle_music,
-            LoopArrangementSettings::default(),
-        ))
-        .unwrap();
-    arrangement_handle.play(InstanceSettings::default());
+// The first level is always open, the rest once the one before is beaten.
+fn is_unlocked(world: &World, i: usize) -> bool {
+    i == 0 || world.campaign.level(i).is_some_and(|l| world.save.is_unlocked(&l.name))
+}
+
+// Plays level `i` from the start, for replays and level select.
+fn start_level(world: &mut World, i: usize) {
+    if world.campaign.select(i) {
+        spawn_player(world);
+    }
+}
+
+// Puts the player at the current level's start with the level fresh.
+fn spawn_player(world: &mut World) {
+    let level = world.campaign.current_mut();
+    level.reset();
+    respawn_player(&mut world.player, level.start);
+    // crossfades when the level changes, and carries on when it's a replay
+    if let Err(e) = world.audio.play_music(level.music.as_deref()) {
+        eprintln!("couldn't play music: {e}");
+    }
+    // the next launch carries on from here
+    if world.save.level.as_ref() != Some(&level.name) {
+        world.save.level = Some(level.name.clone());
+        save_progress(world);
+    }
+}
+
+// Drops the player at `at` standing still, with their jumps back.
+fn respawn_player(player: &mut Player, at: Vec3) {
+    player.trf.translation = at;
+    player.vel = Vec3::zero();
+    player.jump_count = 0;
+    player.jump_buffer = 0.;
+    player.jumping = false;
+    player.contacts = ContactInfo::default();
+}
 
+// Runs once for every death, after the player is already back at their
+// respawn point. Effects and stats that care about dying hang off here.
+fn on_death(world: &mut World, death: Death) {
+    world.death_count += 1;
+    world.last_death = Some(death);
+    play_effect(&mut world.audio, Effect::Death);
+}
+
+// A missing sound isn't worth stopping the game over either.
+fn play_effect(audio: &mut Audio, effect: Effect) {
+    if let Err(e) = audio.play_effect(effect) {
+        eprintln!("couldn't play a sound: {e}");
+    }
+}
+
+fn main() -> Result<()> {
+    frenderer::color_eyre::install()?;
+    let args: Vec<String> = std::env::args().collect();
+    // `jumpyball --validate` just checks the levels and quits
+    if args.iter().any(|a| a == "--validate") {
+        return validate_levels(MANIFEST_FILE).map_err(|e| frenderer::color_eyre::eyre::eyre!("{e}"));
+    }
+    let random_run = random_run_args(&args).map_err(|e| frenderer::color_eyre::eyre::eyre!("{e}"))?;
     let mut engine: Engine = Engine::new(
         FrendererSettings {
             sprite: SpriteRendererSettings {
@@ -360,40 +1138,83 @@ fn main() -> Result<()> {
     let player_mesh = engine.assets().load_textured(std::path::Path::new("content/sphere.obj"))?;
     let player_model = engine.assets().create_textured_model(player_mesh, vec![player_tex]);
 
+    let font = engine.assets().load_font(std::path::Path::new(FONT_FILE))?;
+
     let goal_tex = engine.assets().load_texture(std::path::Path::new("content/gem.png"))?;
     let goal_mesh = engine.assets().load_textured(std::path::Path::new("content/gem.obj"))?;
     let goal_model = engine.assets().create_textured_model(goal_mesh, vec![goal_tex]);
 
-    let level_1 = new_level(
-        &mut engine,
-        "level_1",
-        goal_model.clone(),
-        Vec3::new(-12.75, 10., 11.25),
-        Vec3::new(-15.0, 10.0, -15.0)
-    ).unwrap();
+    let levels = match random_run {
+        Some((seed, difficulty)) => random_levels(&mut engine, seed, &difficulty, goal_model),
+        None => load_levels(&mut engine, MANIFEST_FILE, goal_model),
+    }
+    .map_err(|e| frenderer::color_eyre::eyre::eyre!("{e}"))?;
 
-    let level_2 = new_level(
-        &mut engine,
-        "level_2",
-        goal_model,
-        Vec3::new(14., 4., -14.),
-        Vec3::new(62.0, 8.8, -47.0)
-    ).unwrap();
+    // progress from earlier launches. Random runs only take the settings and
+    // don't write anything back, since their levels differ every seed.
+    let mut save_path = save::default_path();
+    if save_path.is_none() {
+        eprintln!("no config directory, so progress won't be saved");
+    }
+    let (loaded, notice) = match &save_path {
+        Some(path) => SaveData::load_or_back_up(path),
+        None => (None, None),
+    };
+    if let Some(notice) = &notice {
+        eprintln!("{notice}");
+    }
+    let save = loaded.unwrap_or_default();
+    if random_run.is_some() {
+        save_path = None;
+    }
 
-    let levels = vec![level_2];
+    let mut audio = Audio::new().map_err(|e| frenderer::color_eyre::eyre::eyre!("{e}"))?;
+    audio
+        .set_music_volume(save.settings.music_volume)
+        .map_err(|e| frenderer::color_eyre::eyre::eyre!("{e}"))?;
+    audio.set_effects_volume(save.settings.effects_volume);
+    audio.load_effects().map_err(|e| frenderer::color_eyre::eyre::eyre!("{e}"))?;
+    for level in levels.iter() {
+        if let Some(music) = &level.music {
+            audio.load_music(music).map_err(|e| frenderer::color_eyre::eyre::eyre!("{e}"))?;
+        }
+    }
+    let mut campaign = Campaign::new(levels);
+    if let Some(i) = (0..campaign.len()).find(|&i| campaign.level(i).map(|l| &l.name) == save.level.as_ref()) {
+        campaign.select(i);
+    }
+    audio
+        .play_music(campaign.current().music.as_deref())
+        .map_err(|e| frenderer::color_eyre::eyre::eyre!("{e}"))?;
 
     let world: World = World {
         camera,
         camera_control: OrbitCamera::new(),
         player: Player {
-            trf: Similarity3::new(level_1.start, Rotor3::identity(), 1.),
+            trf: Similarity3::new(campaign.current().start, Rotor3::identity(), 1.),
             model: player_model,
-            vy: 0.,
+            vel: Vec3::zero(),
             jump_count: 0,
+            air_time: 0.,
+            jump_buffer: 0.,
+            jumping: false,
+            contacts: ContactInfo::default(),
         },
-        levels,
-        level_i: 0,
-        level: level_1,
+        campaign,
+        complete_timer: 0.,
+        deaths: vec![],
+        death_count: 0,
+        last_death: None,
+        timer: RunTimer::new(),
+        save,
+        save_path,
+        notice,
+        seed: random_run.map(|(seed, _)| seed),
+        editor: None,
+        audio,
+        state: GameState::Title,
+        pause_menu: PauseMenu::new(),
+        font,
     };
 
     engine.play(world)
@@ -0,0 +1,421 @@
+use crate::hazard::{Hazard, HazardKind};
+use crate::files::write_atomically;
+use crate::physics::BoundingBox;
+use frenderer::types::*;
+use serde::Deserialize;
+use std::collections::HashSet;
+use std::ops::Range;
+use std::path::Path;
+
+// Everything the game needs to know to build its levels, read from a RON file
+// like content/levels.ron. File names are relative to content/.
+//
+//   (
+//       levels: [
+//           (
+//               name: "level_1",
+//               mesh: "level_1.obj",
+//               texture: "level_1.png",
+//               collision: Boxes("level_1_bb.txt"),
+//               spawn: (-12.75, 10.0, 11.25),
+//               goals: [(-15.0, 10.0, -15.0)],
+//               collectibles: [(0.0, 10.0, 0.0), (4.0, 10.0, 0.0)],
+//               required: 1,
+//               checkpoints: [(at: (0.0, 9.0, 0.0))],
+//               hazards: [(kind: Lava, at: (0.0, 2.0, 0.0), half: (5.0, 1.0, 5.0))],
+//               music: Some("level_1.ogg"),
+//               death_height: 1.0,
+//           ),
+//       ],
+//   )
+#[derive(Debug, Deserialize)]
+pub struct Manifest {
+    pub levels: Vec<LevelSpec>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct LevelSpec {
+    pub name: String,
+    pub mesh: String,
+    pub texture: String,
+    pub collision: CollisionSpec,
+    pub spawn: (f32, f32, f32),
+    pub goals: Vec<(f32, f32, f32)>,
+    #[serde(default)]
+    pub collectibles: Vec<(f32, f32, f32)>,
+    // collectibles needed before the goals open
+    #[serde(default)]
+    pub required: usize,
+    #[serde(default)]
+    pub checkpoints: Vec<CheckpointSpec>,
+    #[serde(default)]
+    pub hazards: Vec<HazardSpec>,
+    // defaults to the gem every level used to share
+    #[serde(default)]
+    pub goal_model: Option<ModelSpec>,
+    #[serde(default)]
+    pub music: Option<String>,
+    // falling below this sends the player back to the last checkpoint
+    #[serde(default = "default_death_height")]
+    pub death_height: f32,
+    #[serde(default)]
+    pub platforms: Option<String>,
+}
+
+// where a level's static collision comes from
+#[derive(Debug, Deserialize)]
+pub enum CollisionSpec {
+    Boxes(String), // a shape file, see `Shape::from_file`
+    Mesh,          // the triangles of the level's own mesh
+}
+
+// Box the player touches to move their respawn point to `at`.
+#[derive(Debug, Deserialize)]
+pub struct CheckpointSpec {
+    pub at: (f32, f32, f32),
+    #[serde(default = "default_checkpoint_half")]
+    pub half: (f32, f32, f32),
+}
+
+#[derive(Debug, Deserialize)]
+pub struct HazardSpec {
+    pub kind: HazardKind,
+    pub at: (f32, f32, f32),
+    pub half: (f32, f32, f32),
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ModelSpec {
+    pub mesh: String,
+    pub texture: String,
+}
+
+fn default_death_height() -> f32 {
+    1.
+}
+
+fn default_checkpoint_half() -> (f32, f32, f32) {
+    (1., 2., 1.)
+}
+
+impl Manifest {
+    pub fn from_file(filepath: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        let text = std::fs::read_to_string(filepath).map_err(|e| format!("{filepath}: {e}"))?;
+        let manifest = Self::from_ron(&text).map_err(|e| format!("{filepath}: {e}"))?;
+        let dir = Path::new(filepath).parent().unwrap_or(Path::new(""));
+        manifest.check_files(dir).map_err(|e| format!("{filepath}: {e}"))?;
+        Ok(manifest)
+    }
+
+    pub fn from_ron(text: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        let manifest: Self = ron::from_str(text)?;
+        manifest.check()?;
+        Ok(manifest)
+    }
+
+    // Changes the spawn and goals of `level` in the manifest file and nothing
+    // else, so the comments and layout of a hand-written file survive the
+    // editor saving into it.
+    pub fn save_spawn_and_goals(
+        filepath: &str,
+        level: &str,
+        spawn: Vec3,
+        goals: &[Vec3],
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let text = std::fs::read_to_string(filepath).map_err(|e| format!("{filepath}: {e}"))?;
+        let text = with_spawn_and_goals(&text, level, spawn, goals).map_err(|e| format!("{filepath}: {e}"))?;
+        // whatever went in has to come back out the same way
+        Self::from_ron(&text).map_err(|e| format!("{filepath}: after editing level {level:?}: {e}"))?;
+        write_atomically(std::path::Path::new(filepath), &text)
+    }
+
+    // Catches mistakes serde can't, so they show up at load time instead of
+    // halfway through a playthrough.
+    fn check(&self) -> Result<(), String> {
+        if self.levels.is_empty() {
+            return Err("no levels listed".to_string());
+        }
+        let mut names = HashSet::new();
+        for level in &self.levels {
+            if !names.insert(level.name.as_str()) {
+                return Err(format!("level {:?} is listed twice", level.name));
+            }
+            if level.goals.is_empty() {
+                return Err(format!("level {:?} has no goals", level.name));
+            }
+            if level.required > level.collectibles.len() {
+                return Err(format!(
+                    "level {:?} requires {} collectibles but only has {}",
+                    level.name,
+                    level.required,
+                    level.collectibles.len()
+                ));
+            }
+            if let Some(c) = level.checkpoints.iter().find(|c| c.at.1 <= level.death_height) {
+                return Err(format!(
+                    "level {:?} has a checkpoint at {:?} below its death height {}",
+                    level.name, c.at, level.death_height
+                ));
+            }
+            if let Some(h) = level.hazards.iter().find(|h| h.half.0 <= 0. || h.half.1 <= 0. || h.half.2 <= 0.) {
+                return Err(format!("level {:?} has a hazard at {:?} with no size", level.name, h.at));
+            }
+            if level.death_height >= level.spawn.1 {
+                return Err(format!(
+                    "level {:?} spawns at height {} which is below its death height {}",
+                    level.name, level.spawn.1, level.death_height
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    // Every mesh and texture named, looked up next to the manifest, has to be
+    // there; otherwise a typo only shows up once someone reaches that level.
+    fn check_files(&self, dir: &Path) -> Result<(), String> {
+        for level in &self.levels {
+            let models = std::iter::once((&level.mesh, &level.texture))
+                .chain(level.goal_model.iter().map(|m| (&m.mesh, &m.texture)));
+            for file in models.flat_map(|(mesh, texture)| [mesh, texture]) {
+                if !dir.join(file).is_file() {
+                    return Err(format!("level {:?} uses {file:?}, which isn't in {}", level.name, dir.display()));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl LevelSpec {
+    pub fn spawn(&self) -> Vec3 {
+        Vec3::new(self.spawn.0, self.spawn.1, self.spawn.2)
+    }
+
+    pub fn goals(&self) -> impl Iterator<Item = Vec3> + '_ {
+        self.goals.iter().map(|&(x, y, z)| Vec3::new(x, y, z))
+    }
+
+    pub fn collectibles(&self) -> impl Iterator<Item = Vec3> + '_ {
+        self.collectibles.iter().map(|&(x, y, z)| Vec3::new(x, y, z))
+    }
+}
+
+impl CheckpointSpec {
+    pub fn at(&self) -> Vec3 {
+        Vec3::new(self.at.0, self.at.1, self.at.2)
+    }
+
+    pub fn half(&self) -> Vec3 {
+        Vec3::new(self.half.0, self.half.1, self.half.2)
+    }
+}
+
+impl HazardSpec {
+    pub fn hazard(&self) -> Hazard {
+        Hazard {
+            kind: self.kind,
+            volume: BoundingBox::around(
+                Vec3::new(self.at.0, self.at.1, self.at.2),
+                Vec3::new(self.half.0, self.half.1, self.half.2),
+            ),
+        }
+    }
+}
+
+// `src` with the values of `level`'s spawn and goals fields swapped for new
+// ones, leaving every other character alone.
+fn with_spawn_and_goals(src: &str, level: &str, spawn: Vec3, goals: &[Vec3]) -> Result<String, String> {
+    let tokens = tokens(src);
+    let text = |i: usize| &src[tokens[i].clone()];
+    // the level is the innermost tuple around its `name: "..."` field
+    let mut open = vec![];
+    let mut level_start = None;
+    for i in 0..tokens.len() {
+        match text(i) {
+            "(" | "[" | "{" => open.push(i),
+            ")" | "]" | "}" => {
+                open.pop();
+            }
+            "name"
+                if i + 2 < tokens.len()
+                    && text(i + 1) == ":"
+                    && ron::from_str::<String>(text(i + 2)).is_ok_and(|name| name == level) =>
+            {
+                level_start = open.last().copied();
+                break;
+            }
+            _ => {}
+        }
+    }
+    let level_start = level_start.ok_or_else(|| format!("no level {level:?}"))?;
+
+    // the value of each of the level's own fields runs up to the next comma
+    // or the end of the level, at the level's depth
+    let mut depth = 0;
+    let mut edits = vec![];
+    let mut field: Option<(&str, usize)> = None;
+    for i in level_start + 1..tokens.len() {
+        let at_level = depth == 0;
+        match text(i) {
+            "(" | "[" | "{" => depth += 1,
+            ")" | "]" | "}" if depth > 0 => depth -= 1,
+            _ => {}
+        }
+        let end_of_value = at_level && matches!(text(i), "," | ")" | "]" | "}");
+        if let (true, Some((name, from))) = (end_of_value, field) {
+            edits.push((name, tokens[from].start..tokens[i - 1].end));
+            field = None;
+        }
+        if at_level && matches!(text(i), ")" | "]" | "}") {
+            break;
+        }
+        if at_level && i + 2 < tokens.len() && text(i + 1) == ":" && matches!(text(i), "spawn" | "goals") {
+            field = Some((text(i), i + 2));
+        }
+    }
+
+    for name in ["spawn", "goals"] {
+        if !edits.iter().any(|(n, _)| *n == name) {
+            return Err(format!("level {level:?} has no {name}"));
+        }
+    }
+    let point = |p: Vec3| format!("({:?}, {:?}, {:?})", p.x, p.y, p.z);
+    let mut out = src.to_string();
+    // from the back, so the ranges still to go don't move
+    edits.sort_by_key(|(_, range)| std::cmp::Reverse(range.start));
+    for (name, range) in edits {
+        let value = match name {
+            "spawn" => point(spawn),
+            _ => format!("[{}]", goals.iter().map(|&g| point(g)).collect::<Vec<_>>().join(", ")),
+        };
+        out.replace_range(range, &value);
+    }
+    Ok(out)
+}
+
+// Byte ranges of the RON tokens in `src` that matter for finding fields:
+// brackets, commas, colons, whole strings and runs of anything else. Comments
+// and whitespace are skipped.
+fn tokens(src: &str) -> Vec<Range<usize>> {
+    let bytes = src.as_bytes();
+    let mut tokens = vec![];
+    let mut i = 0;
+    while i < bytes.len() {
+        let start = i;
+        match bytes[i] {
+            b if b.is_ascii_whitespace() => {
+                i += 1;
+                continue;
+            }
+            b'/' if bytes.get(i + 1) == Some(&b'/') => {
+                i = src[i..].find('\n').map_or(bytes.len(), |n| i + n);
+                continue;
+            }
+            b'/' if bytes.get(i + 1) == Some(&b'*') => {
+                i = src[i + 2..].find("*/").map_or(bytes.len(), |n| i + n + 4);
+                continue;
+            }
+            b'"' => {
+                i += 1;
+                while i < bytes.len() && bytes[i] != b'"' {
+                    i += if bytes[i] == b'\\' { 2 } else { 1 };
+                }
+                i = (i + 1).min(bytes.len());
+            }
+            b'(' | b')' | b'[' | b']' | b'{' | b'}' | b',' | b':' => i += 1,
+            _ => {
+                while i < bytes.len()
+                    && !bytes[i].is_ascii_whitespace()
+                    && !b"()[]{},:\"".contains(&bytes[i])
+                    && !src[i..].starts_with("//")
+                    && !src[i..].starts_with("/*")
+                {
+                    i += 1;
+                }
+            }
+        }
+        tokens.push(start..i);
+    }
+    tokens
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SRC: &str = r#"(
+    levels: [
+        // the tutorial
+        (
+            name: "one",
+            mesh: "one.obj", texture: "one.png",
+            collision: Boxes("one_bb.txt"),
+            spawn: (1.0, 2.0, 3.0), // on the ledge
+            goals: [(4.0, 5.0, 6.0)],
+            checkpoints: [(at: (0.0, 9.0, 0.0))],
+        ),
+        (
+            name: "two",
+            mesh: "two.obj",
+            texture: "two.png",
+            collision: Mesh,
+            /* spawn: (0.0, 0.0, 0.0), */
+            spawn: (7.0, 8.0, 9.0),
+            goals: [
+                (1.0, 2.0, 3.0),
+            ]
+        ),
+    ],
+)
+"#;
+
+    #[test]
+    fn editing_keeps_everything_else() {
+        let spawn = Vec3::new(-1.5, 10.0, 0.25);
+        let goals = [Vec3::new(0., 1., 2.), Vec3::new(3., 4., 5.)];
+        let edited = with_spawn_and_goals(SRC, "two", spawn, &goals).unwrap();
+        let expected = SRC
+            .replace("spawn: (7.0, 8.0, 9.0)", "spawn: (-1.5, 10.0, 0.25)")
+            .replace(
+                "goals: [\n                (1.0, 2.0, 3.0),\n            ]",
+                "goals: [(0.0, 1.0, 2.0), (3.0, 4.0, 5.0)]",
+            );
+        assert_eq!(edited, expected);
+
+        let edited = with_spawn_and_goals(SRC, "one", spawn, &goals[..1]).unwrap();
+        let expected = SRC
+            .replace("spawn: (1.0, 2.0, 3.0)", "spawn: (-1.5, 10.0, 0.25)")
+            .replace("goals: [(4.0, 5.0, 6.0)]", "goals: [(0.0, 1.0, 2.0)]");
+        assert_eq!(edited, expected);
+        assert!(Manifest::from_ron(&edited).is_ok());
+    }
+
+    #[test]
+    fn missing_meshes_and_textures_are_caught() {
+        let manifest = Manifest::from_ron(SRC).unwrap();
+        let dir = std::env::temp_dir().join(format!("jumpyball_manifest_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let mut missing = vec![];
+        for file in ["one.obj", "one.png", "two.obj", "two.png"] {
+            missing.push(manifest.check_files(&dir).unwrap_err());
+            std::fs::write(dir.join(file), "").unwrap();
+        }
+        let found = manifest.check_files(&dir);
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(found, Ok(()));
+        for (e, file) in missing.iter().zip(["one.obj", "one.png", "two.obj", "two.png"]) {
+            assert!(e.contains(&format!("{file:?}")), "{e}");
+        }
+    }
+
+    #[test]
+    fn the_shipped_manifest_loads() {
+        Manifest::from_file(concat!(env!("CARGO_MANIFEST_DIR"), "/content/levels.ron")).unwrap();
+    }
+
+    #[test]
+    fn editing_a_missing_level_fails() {
+        let e = with_spawn_and_goals(SRC, "three", Vec3::zero(), &[Vec3::zero()]).unwrap_err();
+        assert!(e.contains("no level \"three\""), "{e}");
+    }
+}
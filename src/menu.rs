@@ -0,0 +1,162 @@
+use crate::editor::pressed_axis;
+use frenderer::{Input, Key};
+
+// What the player did to a menu this tick, already turned from keys into
+// directions so menus can be driven without a window.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MenuInput {
+    Up,
+    Down,
+    Left,
+    Right,
+    Select,
+    Back,
+}
+
+impl MenuInput {
+    // Arrows move, Return picks, Escape backs out. At most one per tick.
+    pub fn from_keys(input: &Input) -> Option<Self> {
+        match (
+            pressed_axis(input, Key::Down, Key::Up),
+            pressed_axis(input, Key::Right, Key::Left),
+        ) {
+            (y, _) if y < 0. => Some(MenuInput::Up),
+            (y, _) if y > 0. => Some(MenuInput::Down),
+            (_, x) if x < 0. => Some(MenuInput::Left),
+            (_, x) if x > 0. => Some(MenuInput::Right),
+            _ if input.is_key_pressed(Key::Return) => Some(MenuInput::Select),
+            _ if input.is_key_pressed(Key::Escape) => Some(MenuInput::Back),
+            _ => None,
+        }
+    }
+}
+
+// Things the pause menu asks the game to do.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PauseAction {
+    Resume,
+    RestartLevel,
+    Quit,
+    MusicVolume(i8), // notches up or down
+    EffectsVolume(i8),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PauseItem {
+    Resume,
+    RestartLevel,
+    Settings,
+    Quit,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SettingsItem {
+    MusicVolume,
+    EffectsVolume,
+    Back,
+}
+
+const PAUSE_ITEMS: [PauseItem; 4] = [
+    PauseItem::Resume,
+    PauseItem::RestartLevel,
+    PauseItem::Settings,
+    PauseItem::Quit,
+];
+const SETTINGS_ITEMS: [SettingsItem; 3] = [
+    SettingsItem::MusicVolume,
+    SettingsItem::EffectsVolume,
+    SettingsItem::Back,
+];
+
+// The pause menu and its settings page. Escape backs out of settings, and
+// out of the menu altogether from the top.
+#[derive(Clone, Debug, Default)]
+pub struct PauseMenu {
+    selected: usize,
+    in_settings: bool,
+}
+
+impl PauseMenu {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn in_settings(&self) -> bool {
+        self.in_settings
+    }
+
+    pub fn selected(&self) -> usize {
+        self.selected
+    }
+
+    pub fn handle(&mut self, input: MenuInput) -> Option<PauseAction> {
+        let len = if self.in_settings { SETTINGS_ITEMS.len() } else { PAUSE_ITEMS.len() };
+        match input {
+            MenuInput::Up => self.selected = (self.selected + len - 1) % len,
+            MenuInput::Down => self.selected = (self.selected + 1) % len,
+            MenuInput::Back if self.in_settings => self.leave_settings(),
+            MenuInput::Back => return self.close(PauseAction::Resume),
+            MenuInput::Left | MenuInput::Right | MenuInput::Select if self.in_settings => {
+                let notch = if input == MenuInput::Left { -1 } else { 1 };
+                match SETTINGS_ITEMS[self.selected] {
+                    SettingsItem::MusicVolume if input != MenuInput::Select => {
+                        return Some(PauseAction::MusicVolume(notch))
+                    }
+                    SettingsItem::EffectsVolume if input != MenuInput::Select => {
+                        return Some(PauseAction::EffectsVolume(notch))
+                    }
+                    SettingsItem::Back if input == MenuInput::Select => self.leave_settings(),
+                    _ => {}
+                }
+            }
+            MenuInput::Select => match PAUSE_ITEMS[self.selected] {
+                PauseItem::Resume => return self.close(PauseAction::Resume),
+                PauseItem::RestartLevel => return self.close(PauseAction::RestartLevel),
+                PauseItem::Settings => {
+                    self.in_settings = true;
+                    self.selected = 0;
+                }
+                PauseItem::Quit => return self.close(PauseAction::Quit),
+            },
+            MenuInput::Left | MenuInput::Right => {}
+        }
+        None
+    }
+
+    // One line per item on the current page, for drawing.
+    pub fn labels(&self, music_volume: f64, effects_volume: f64) -> Vec<String> {
+        let percent = |v: f64| (v * 100.).round() as u32;
+        if self.in_settings {
+            SETTINGS_ITEMS
+                .iter()
+                .map(|item| match item {
+                    SettingsItem::MusicVolume => format!("Music volume < {}% >", percent(music_volume)),
+                    SettingsItem::EffectsVolume => format!("Effects volume < {}% >", percent(effects_volume)),
+                    SettingsItem::Back => "Back".to_string(),
+                })
+                .collect()
+        } else {
+            PAUSE_ITEMS
+                .iter()
+                .map(|item| match item {
+                    PauseItem::Resume => "Resume",
+                    PauseItem::RestartLevel => "Restart level",
+                    PauseItem::Settings => "Settings",
+                    PauseItem::Quit => "Quit to title",
+                })
+                .map(str::to_string)
+                .collect()
+        }
+    }
+
+    fn leave_settings(&mut self) {
+        self.in_settings = false;
+        self.selected = PAUSE_ITEMS.iter().position(|&i| i == PauseItem::Settings).unwrap();
+    }
+
+    // the menu opens fresh at the top the next time the game is paused
+    fn close(&mut self, action: PauseAction) -> Option<PauseAction> {
+        *self = Self::default();
+        Some(action)
+    }
+}
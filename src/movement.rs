@@ -0,0 +1,146 @@
+use crate::physics::Material;
+use frenderer::types::*;
+
+// How the ball moves, taken from the game's movement settings. Everything is
+// per second so that the same settings give the same motion at any tick rate.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Movement {
+    pub top_speed: f32,   // units/second
+    pub accel: f32,       // units/second^2 while rolling
+    pub air_control: f32, // fraction of `accel` available in the air
+    pub friction: f32,    // units/second^2 of slowing down on the ground
+    pub drag: f32,        // fraction of speed lost per second
+    pub gravity: f32,     // units/second^2, negative is down
+}
+
+impl Movement {
+    // The horizontal velocity after a `dt` second tick of pushing toward
+    // `wish` (at most unit length) while on `ground`, or in the air for None.
+    pub fn roll(&self, vel: Vec3, wish: Vec3, ground: Option<Material>, dt: f32) -> Vec3 {
+        // rolling happens relative to whatever the ball sits on, so conveyors
+        // carry it along and icy floors give it little to push against
+        let surface_vel = ground.map_or(Vec3::zero(), |m| Vec3::new(m.surface_velocity.x, 0., m.surface_velocity.z));
+        let mut flat = Vec3::new(vel.x, 0., vel.z) - surface_vel;
+        flat += wish * match ground {
+            Some(m) => self.accel * m.friction.min(1.),
+            None => self.accel * self.air_control,
+        } * dt;
+        if let Some(m) = ground {
+            if wish.mag_sq() == 0. {
+                // rolling resistance only bites when nothing is pushing the ball
+                let speed = flat.mag();
+                if speed > 0. {
+                    flat *= (speed - self.friction * m.friction * dt).max(0.) / speed;
+                }
+            }
+        }
+        flat *= (-self.drag * dt).exp();
+        if flat.mag() > self.top_speed {
+            flat = flat.normalized() * self.top_speed;
+        }
+        flat += surface_vel;
+        Vec3::new(flat.x, vel.y, flat.z)
+    }
+
+    // The velocity to move with for a `dt` second tick. Only half the tick's
+    // gravity is in it; `after_move` adds the rest, so the arc is exact for
+    // any dt instead of drifting with the tick rate.
+    pub fn before_move(&self, vel: Vec3, dt: f32) -> Vec3 {
+        vel + Vec3::unit_y() * 0.5 * self.gravity * dt
+    }
+
+    // The other half of the tick's gravity, once the ball has moved.
+    pub fn after_move(&self, vel: Vec3, dt: f32) -> Vec3 {
+        vel + Vec3::unit_y() * 0.5 * self.gravity * dt
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MOVEMENT: Movement = Movement {
+        top_speed: 12.,
+        accel: 30.,
+        air_control: 0.4,
+        friction: 12.,
+        drag: 0.5,
+        gravity: -108.,
+    };
+    const JUMP_SPEED: f32 = 36.;
+    const RATES: [f32; 3] = [30., 60., 120.];
+
+    #[test]
+    fn jump_height_is_the_same_at_any_rate() {
+        let expected = JUMP_SPEED * JUMP_SPEED / (-2. * MOVEMENT.gravity);
+        for rate in RATES {
+            let dt = 1. / rate;
+            let (mut pos, mut vel, mut apex) = (Vec3::zero(), Vec3::unit_y() * JUMP_SPEED, 0f32);
+            for _ in 0..rate as usize {
+                vel = MOVEMENT.before_move(vel, dt);
+                pos += vel * dt;
+                vel = MOVEMENT.after_move(vel, dt);
+                apex = apex.max(pos.y);
+            }
+            assert!((apex - expected).abs() < 1e-3, "{rate} Hz: apex {apex}, expected {expected}");
+        }
+    }
+
+    // the speed after pushing along x on ordinary ground for `seconds`
+    fn speed_after(rate: f32, seconds: f32) -> f32 {
+        let mut vel = Vec3::zero();
+        for _ in 0..(seconds * rate).round() as usize {
+            vel = MOVEMENT.roll(vel, Vec3::unit_x(), Some(Material::default()), 1. / rate);
+        }
+        vel.x
+    }
+
+    #[test]
+    fn top_speed_is_the_same_at_any_rate() {
+        for rate in RATES {
+            let speed = speed_after(rate, 3.);
+            assert!((speed - MOVEMENT.top_speed).abs() < 1e-4, "{rate} Hz: {speed}");
+        }
+    }
+
+    #[test]
+    fn speeding_up_is_the_same_at_any_rate() {
+        let expected = speed_after(60., 0.2);
+        for rate in RATES {
+            let speed = speed_after(rate, 0.2);
+            assert!((speed - expected).abs() < expected * 0.01, "{rate} Hz: {speed} against {expected}");
+        }
+    }
+
+    #[test]
+    fn friction_stops_the_ball_on_the_ground_only() {
+        let moving = Vec3::unit_x() * 6.;
+        let mut on_ground = moving;
+        let mut in_air = moving;
+        for _ in 0..60 {
+            on_ground = MOVEMENT.roll(on_ground, Vec3::zero(), Some(Material::default()), 1. / 60.);
+            in_air = MOVEMENT.roll(in_air, Vec3::zero(), None, 1. / 60.);
+        }
+        assert_eq!(on_ground, Vec3::zero());
+        // only drag slows it down in the air
+        assert!((in_air.x - 6. * (-MOVEMENT.drag).exp()).abs() < 1e-3, "{in_air:?}");
+    }
+
+    #[test]
+    fn conveyors_carry_a_still_ball() {
+        let belt = Material::parse(&["conveyor", "3", "0", "0"]).unwrap();
+        let mut vel = Vec3::zero();
+        for _ in 0..120 {
+            vel = MOVEMENT.roll(vel, Vec3::zero(), Some(belt), 1. / 60.);
+        }
+        assert!((vel - Vec3::unit_x() * 3.).mag() < 1e-4, "{vel:?}");
+    }
+
+    #[test]
+    fn ice_gives_less_to_push_against() {
+        let ice = Material::parse(&["ice"]).unwrap();
+        let on_ice = MOVEMENT.roll(Vec3::zero(), Vec3::unit_x(), Some(ice), 1. / 60.);
+        let on_ground = MOVEMENT.roll(Vec3::zero(), Vec3::unit_x(), Some(Material::default()), 1. / 60.);
+        assert!((on_ice.x - on_ground.x * ice.friction).abs() < 1e-5, "{on_ice:?} against {on_ground:?}");
+    }
+}
@@ -0,0 +1,156 @@
+use std::fmt;
+
+// Something wrong in a text data file, and exactly where.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseError {
+    pub file: String,
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}:{}: {}", self.file, self.line, self.column, self.message)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+// Lines worth parsing in a data file as (line number, text), leaving out
+// blank lines and `#` comments, whole-line or trailing.
+pub fn data_lines(filepath: &str) -> Result<Vec<(usize, String)>, Box<dyn std::error::Error>> {
+    let text = std::fs::read_to_string(filepath).map_err(|e| format!("{filepath}: {e}"))?;
+    Ok(text
+        .lines()
+        .enumerate()
+        .map(|(i, line)| (i + 1, line.split('#').next().unwrap_or("").to_string()))
+        .filter(|(_, line)| !line.trim().is_empty())
+        .collect())
+}
+
+// A whitespace separated word and the column it starts at, counting from 1.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Word<'a> {
+    pub text: &'a str,
+    pub column: usize,
+}
+
+// Walks the words of one line, turning anything missing or malformed into a
+// ParseError that points at it.
+pub struct LineCursor<'a> {
+    file: &'a str,
+    line: usize,
+    end_column: usize,
+    words: Vec<Word<'a>>,
+    next: usize,
+}
+
+impl<'a> LineCursor<'a> {
+    pub fn new(file: &'a str, line: usize, text: &'a str) -> Self {
+        let mut words = vec![];
+        let mut start = None;
+        for (column, (byte, ch)) in text.char_indices().enumerate() {
+            match (ch.is_whitespace(), start) {
+                (false, None) => start = Some((byte, column + 1)),
+                (true, Some((from, column))) => {
+                    words.push(Word { text: &text[from..byte], column });
+                    start = None;
+                }
+                _ => {}
+            }
+        }
+        if let Some((from, column)) = start {
+            words.push(Word { text: &text[from..], column });
+        }
+        Self {
+            file,
+            line,
+            end_column: text.trim_end().chars().count() + 1,
+            words,
+            next: 0,
+        }
+    }
+
+    pub fn error(&self, column: usize, message: impl Into<String>) -> ParseError {
+        ParseError {
+            file: self.file.to_string(),
+            line: self.line,
+            column,
+            message: message.into(),
+        }
+    }
+
+    pub fn peek(&self) -> Option<Word<'a>> {
+        self.words.get(self.next).copied()
+    }
+
+    // where the next word is, or the end of the line if there isn't one
+    pub fn column(&self) -> usize {
+        self.peek().map_or(self.end_column, |w| w.column)
+    }
+
+    pub fn word(&mut self, what: &str) -> Result<Word<'a>, ParseError> {
+        let word = self
+            .peek()
+            .ok_or_else(|| self.error(self.end_column, format!("expected {what}")))?;
+        self.next += 1;
+        Ok(word)
+    }
+
+    // a finite number; nan and inf would break every comparison made with them
+    pub fn float(&mut self, what: &str) -> Result<f32, ParseError> {
+        let word = self.word(what)?;
+        match word.text.parse::<f32>() {
+            Ok(x) if x.is_finite() => Ok(x),
+            Ok(_) => Err(self.error(word.column, format!("{what} must be a finite number, found {:?}", word.text))),
+            Err(_) => Err(self.error(word.column, format!("expected {what}, found {:?}", word.text))),
+        }
+    }
+
+    // a number that may be left off the end of the line
+    pub fn optional_float(&mut self, what: &str) -> Result<Option<f32>, ParseError> {
+        match self.peek() {
+            Some(w) if w.text.parse::<f32>().is_ok() => self.float(what).map(Some),
+            _ => Ok(None),
+        }
+    }
+
+    // everything not read yet
+    pub fn rest(&mut self) -> Vec<Word<'a>> {
+        let rest = self.words[self.next..].to_vec();
+        self.next = self.words.len();
+        rest
+    }
+
+    pub fn finish(&self) -> Result<(), ParseError> {
+        match self.peek() {
+            Some(w) => Err(self.error(w.column, format!("unexpected {:?}", w.text))),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn floats_must_be_finite() {
+        for text in ["1 nan", "1 inf", "1 -inf", "1 NaN"] {
+            let mut line = LineCursor::new("test.txt", 3, text);
+            assert_eq!(line.float("x"), Ok(1.));
+            let e = line.float("y").unwrap_err();
+            assert_eq!((e.line, e.column), (3, 3), "{text}: {e}");
+            assert!(e.message.contains("finite"), "{text}: {e}");
+        }
+    }
+
+    #[test]
+    fn malformed_floats_say_where() {
+        let mut line = LineCursor::new("test.txt", 1, "  1.5  x2");
+        assert_eq!(line.float("x"), Ok(1.5));
+        let e = line.float("y").unwrap_err();
+        assert_eq!(e.to_string(), "test.txt:1:8: expected y, found \"x2\"");
+    }
+}
@@ -0,0 +1,503 @@
+use frenderer::types::*;
+
+pub mod broadphase;
+pub mod material;
+pub mod shapes;
+use broadphase::Bvh;
+pub use material::{Material, MaterialKind};
+pub use shapes::Shape;
+
+// max number of sweep/slide passes per tick
+const MAX_SWEEPS: usize = 4;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BoundingBox {
+    pub min_x: f32,
+    pub max_x: f32,
+    pub min_y: f32,
+    pub max_y: f32,
+    pub min_z: f32,
+    pub max_z: f32,
+}
+
+impl BoundingBox {
+    pub fn new(min_x: f32, max_x: f32, min_y: f32, max_y: f32, min_z: f32, max_z: f32) -> Self {
+        Self {
+            min_x,
+            max_x,
+            min_y,
+            max_y,
+            min_z,
+            max_z
+        }
+    }
+
+    pub fn around(center: Vec3, half: Vec3) -> Self {
+        Self::new(
+            center.x - half.x,
+            center.x + half.x,
+            center.y - half.y,
+            center.y + half.y,
+            center.z - half.z,
+            center.z + half.z,
+        )
+    }
+
+    pub fn min(&self) -> Vec3 {
+        Vec3::new(self.min_x, self.min_y, self.min_z)
+    }
+
+    pub fn max(&self) -> Vec3 {
+        Vec3::new(self.max_x, self.max_y, self.max_z)
+    }
+
+    pub fn center(&self) -> Vec3 {
+        (self.min() + self.max()) * 0.5
+    }
+
+    pub fn expanded(&self, r: f32) -> Self {
+        Self::new(
+            self.min_x - r,
+            self.max_x + r,
+            self.min_y - r,
+            self.max_y + r,
+            self.min_z - r,
+            self.max_z + r,
+        )
+    }
+
+    pub fn union(&self, other: &Self) -> Self {
+        Self::new(
+            self.min_x.min(other.min_x),
+            self.max_x.max(other.max_x),
+            self.min_y.min(other.min_y),
+            self.max_y.max(other.max_y),
+            self.min_z.min(other.min_z),
+            self.max_z.max(other.max_z),
+        )
+    }
+
+    pub fn overlaps(&self, other: &Self) -> bool {
+        self.min_x <= other.max_x
+            && other.min_x <= self.max_x
+            && self.min_y <= other.max_y
+            && other.min_y <= self.max_y
+            && self.min_z <= other.max_z
+            && other.min_z <= self.max_z
+    }
+
+    pub fn closest_point(&self, p: Vec3) -> Vec3 {
+        Vec3::new(
+            p.x.clamp(self.min_x, self.max_x),
+            p.y.clamp(self.min_y, self.max_y),
+            p.z.clamp(self.min_z, self.max_z),
+        )
+    }
+}
+
+pub struct Sphere {
+    pub pos: Vec3,
+    pub r: f32,
+}
+
+impl Sphere {
+    pub fn touching(&self, b: &BoundingBox) -> bool {
+        (b.closest_point(self.pos) - self.pos).mag() <= self.r
+    }
+}
+
+// The level's static collision shapes along with the broadphase built over them.
+// Anything that changes `shapes` should call `rebuild` afterwards. Moving
+// platforms live outside the broadphase and are checked one by one.
+// `materials` lines up with `shapes`; platforms are always plain ground.
+pub struct Colliders {
+    pub shapes: Vec<Shape>,
+    pub materials: Vec<Material>,
+    pub platforms: Vec<Shape>,
+    bvh: Bvh,
+}
+
+// which collision shape something refers to
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Collider {
+    Level(usize),
+    Platform(usize),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RayHit {
+    pub collider: Collider,
+    pub t: f32,
+    pub point: Vec3,
+}
+
+impl Colliders {
+    pub fn new(shapes: Vec<Shape>) -> Self {
+        let materials = vec![Material::default(); shapes.len()];
+        Self::with_materials(shapes, materials)
+    }
+
+    pub fn with_materials(shapes: Vec<Shape>, materials: Vec<Material>) -> Self {
+        let bvh = Bvh::build(&shapes.iter().map(Shape::bounds).collect::<Vec<_>>());
+        Self {
+            shapes,
+            materials,
+            platforms: vec![],
+            bvh,
+        }
+    }
+
+    pub fn rebuild(&mut self) {
+        self.bvh = Bvh::build(&self.shapes.iter().map(Shape::bounds).collect::<Vec<_>>());
+    }
+
+    pub fn shape(&self, collider: Collider) -> &Shape {
+        match collider {
+            Collider::Level(i) => &self.shapes[i],
+            Collider::Platform(i) => &self.platforms[i],
+        }
+    }
+
+    pub fn material(&self, collider: Collider) -> Material {
+        match collider {
+            Collider::Level(i) => self.materials[i],
+            Collider::Platform(_) => Material::default(),
+        }
+    }
+
+    // static shapes the broadphase picked out, followed by every platform
+    fn candidates(&self, query: impl FnOnce(&Bvh, &mut Vec<usize>)) -> impl Iterator<Item = Collider> {
+        let mut found = vec![];
+        query(&self.bvh, &mut found);
+        found
+            .into_iter()
+            .map(Collider::Level)
+            .chain((0..self.platforms.len()).map(Collider::Platform))
+    }
+
+    pub fn overlapping_sphere(&self, pos: Vec3, r: f32) -> Vec<Collider> {
+        self.candidates(|bvh, found| bvh.query_sphere(pos, r, found))
+            .filter(|&c| self.shape(c).touching_sphere(pos, r))
+            .collect()
+    }
+
+    // First shape hit by the segment from `origin` to `origin + d`, where `t` is
+    // the fraction of `d` travelled.
+    pub fn ray_cast(&self, origin: Vec3, d: Vec3) -> Option<RayHit> {
+        self.candidates(|bvh, found| bvh.query_ray(origin, d, found))
+            .filter_map(|c| self.shape(c).ray(origin, d).map(|t| (t, c)))
+            .min_by(|(t1, _), (t2, _)| t1.partial_cmp(t2).unwrap())
+            .map(|(t, collider)| RayHit {
+                collider,
+                t,
+                point: origin + d * t,
+            })
+    }
+
+    // Earliest time of impact of a sphere moving by `motion`.
+    pub fn sweep_sphere(&self, pos: Vec3, motion: Vec3, r: f32) -> Option<(f32, Collider)> {
+        self.candidates(|bvh, found| bvh.query_sweep(pos, motion, r, found))
+            .filter_map(|c| self.shape(c).sweep_sphere(pos, motion, r).map(|t| (t, c)))
+            .min_by(|(t1, _), (t2, _)| t1.partial_cmp(t2).unwrap())
+    }
+}
+
+// contact normals with at least this much y count as standing on something
+const GROUND_NORMAL_Y: f32 = 0.7;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Contact {
+    pub collider: Collider,
+    pub point: Vec3,
+    pub normal: Vec3,
+}
+
+impl Contact {
+    pub fn is_ground(&self) -> bool {
+        self.normal.y >= GROUND_NORMAL_Y
+    }
+
+    pub fn is_ceiling(&self) -> bool {
+        self.normal.y <= -GROUND_NORMAL_Y
+    }
+
+    pub fn is_wall(&self) -> bool {
+        !self.is_ground() && !self.is_ceiling()
+    }
+}
+
+// everything the sphere touched during one move
+#[derive(Clone, Default, Debug)]
+pub struct ContactInfo {
+    pub contacts: Vec<Contact>,
+}
+
+impl ContactInfo {
+    pub fn grounded(&self) -> bool {
+        self.contacts.iter().any(Contact::is_ground)
+    }
+
+    pub fn ground(&self) -> Option<&Contact> {
+        self.contacts
+            .iter()
+            .filter(|c| c.is_ground())
+            .max_by(|c1, c2| c1.normal.y.partial_cmp(&c2.normal.y).unwrap())
+    }
+
+    pub fn hit_ceiling(&self) -> bool {
+        self.contacts.iter().any(Contact::is_ceiling)
+    }
+
+    pub fn touching_wall(&self) -> bool {
+        self.contacts.iter().any(Contact::is_wall)
+    }
+
+    pub fn touching(&self, collider: Collider) -> bool {
+        self.contacts.iter().any(|c| c.collider == collider)
+    }
+
+    fn add(&mut self, contact: Contact) {
+        // keep one contact per shape, the latest one wins
+        match self.contacts.iter_mut().find(|c| c.collider == contact.collider) {
+            Some(c) => *c = contact,
+            None => self.contacts.push(contact),
+        }
+    }
+}
+
+// Moves a sphere at `vel` (units/second) for `dt` seconds against the level
+// shapes, stopping at the earliest time of impact instead of only testing the
+// end position, so fast-moving spheres can't skip over thin platforms. On
+// impact the leftover motion loses the part going into the surface, so the
+// sphere slides along it, and `vel` goes through the surface's material.
+pub fn move_sphere(pos: &mut Vec3, vel: &mut Vec3, r: f32, dt: f32, colliders: &Colliders) -> ContactInfo {
+    let mut contacts = ContactInfo::default();
+    let mut remaining = *vel * dt;
+
+    for _ in 0..MAX_SWEEPS {
+        if remaining.mag_sq() == 0. {
+            break;
+        }
+
+        match colliders.sweep_sphere(*pos, remaining, r) {
+            Some((t, c)) => {
+                *pos += remaining * t;
+                remaining *= 1. - t;
+
+                let contact = contact_with(*pos, c, colliders.shape(c), remaining);
+                remaining = slide(remaining, contact.normal);
+                *vel = colliders.material(c).respond(*vel, contact.normal);
+                contacts.add(contact);
+            }
+            None => {
+                *pos += remaining;
+                break;
+            }
+        }
+    }
+
+    // anything still overlapping gets pushed out along its contact normal
+    for c in colliders.overlapping_sphere(*pos, r) {
+        if let Some(contact) = push_out(pos, r, c, colliders.shape(c)) {
+            *vel = colliders.material(c).respond(*vel, contact.normal);
+            contacts.add(contact);
+        }
+    }
+
+    contacts
+}
+
+// Removes the part of `v` heading into a surface with normal `n`.
+pub fn slide(v: Vec3, n: Vec3) -> Vec3 {
+    v - n * v.dot(n).min(0.)
+}
+
+pub fn push_out(pos: &mut Vec3, r: f32, collider: Collider, shape: &Shape) -> Option<Contact> {
+    let (dist, point, normal) = shape.query(*pos, Vec3::zero());
+
+    if dist >= r {
+        return None;
+    }
+
+    // when the center is inside, dist is negative and this gets it all the way out
+    *pos += normal * (r - dist);
+    Some(Contact {
+        collider,
+        point,
+        normal,
+    })
+}
+
+fn contact_with(pos: Vec3, collider: Collider, shape: &Shape, motion: Vec3) -> Contact {
+    let (point, normal) = shape.surface(pos, motion);
+    Contact {
+        collider,
+        point,
+        normal,
+    }
+}
+
+// Earliest fraction of `motion` (0 to 1) at which a sphere starting at `c`
+// touches the box, or None if it never does during this move.
+pub fn sweep_sphere_box(c: Vec3, motion: Vec3, r: f32, b: &BoundingBox) -> Option<f32> {
+    let offset = c - b.closest_point(c);
+    if offset.mag_sq() <= r * r {
+        // already touching: only a hit if we're moving further in
+        return if offset.dot(motion) < -1e-6 || offset.mag_sq() == 0. {
+            Some(0.)
+        } else {
+            None
+        };
+    }
+
+    // the sphere's center hits the box grown by r, but with rounded edges and corners
+    let t = ray_box(c, motion, &b.expanded(r))?;
+    let p = c + motion * t;
+
+    let (min, max) = (b.min(), b.max());
+    let below = [p.x < min.x, p.y < min.y, p.z < min.z];
+    let above = [p.x > max.x, p.y > max.y, p.z > max.z];
+    let outside = (0..3).filter(|&i| below[i] || above[i]).count();
+    if outside <= 1 {
+        // flat face region, the grown box is exact here
+        return Some(t);
+    }
+
+    // edge or corner region: test against the box edges as capsules
+    let corner = Vec3::new(
+        if below[0] { min.x } else { max.x },
+        if below[1] { min.y } else { max.y },
+        if below[2] { min.z } else { max.z },
+    );
+    (0..3)
+        .filter(|&i| outside == 3 || !(below[i] || above[i]))
+        .filter_map(|i| {
+            let mut a = corner;
+            let mut e = corner;
+            a[i] = min[i];
+            e[i] = max[i];
+            sweep_sphere_segment(c, motion, r, a, e)
+        })
+        .min_by(|t1, t2| t1.partial_cmp(t2).unwrap())
+}
+
+// Slab test, clamped to the segment from `c` to `c + d`.
+pub fn ray_box(c: Vec3, d: Vec3, b: &BoundingBox) -> Option<f32> {
+    let (min, max) = (b.min(), b.max());
+    let mut t_min = 0.0_f32;
+    let mut t_max = 1.0_f32;
+
+    for i in 0..3 {
+        if d[i].abs() < f32::EPSILON {
+            if c[i] < min[i] || c[i] > max[i] {
+                return None;
+            }
+        } else {
+            let t1 = (min[i] - c[i]) / d[i];
+            let t2 = (max[i] - c[i]) / d[i];
+            t_min = t_min.max(t1.min(t2));
+            t_max = t_max.min(t1.max(t2));
+            if t_min > t_max {
+                return None;
+            }
+        }
+    }
+
+    Some(t_min)
+}
+
+fn sweep_sphere_point(c: Vec3, d: Vec3, r: f32, p: Vec3) -> Option<f32> {
+    let m = c - p;
+    let a = d.dot(d);
+    let b = m.dot(d);
+    let k = m.dot(m) - r * r;
+    if k <= 0. {
+        return Some(0.);
+    }
+    if a == 0. || b >= 0. {
+        return None;
+    }
+    let disc = b * b - a * k;
+    if disc < 0. {
+        return None;
+    }
+    let t = (-b - disc.sqrt()) / a;
+    if t <= 1. { Some(t.max(0.)) } else { None }
+}
+
+fn sweep_sphere_segment(c: Vec3, d: Vec3, r: f32, a: Vec3, e: Vec3) -> Option<f32> {
+    let axis = e - a;
+    let len_sq = axis.dot(axis);
+    if len_sq == 0. {
+        return sweep_sphere_point(c, d, r, a);
+    }
+    let m = c - a;
+
+    // the infinite cylinder around the segment, ignoring the axis direction
+    let mp = m - axis * (m.dot(axis) / len_sq);
+    let dp = d - axis * (d.dot(axis) / len_sq);
+    let qa = dp.dot(dp);
+    let qb = mp.dot(dp);
+    let qk = mp.dot(mp) - r * r;
+    if qa > 0. && qk > 0. && qb < 0. {
+        let disc = qb * qb - qa * qk;
+        if disc >= 0. {
+            let t = (-qb - disc.sqrt()) / qa;
+            let s = (m + d * t).dot(axis) / len_sq;
+            if t <= 1. && (0. ..=1.).contains(&s) {
+                return Some(t.max(0.));
+            }
+        }
+    }
+
+    // otherwise it can only hit one of the rounded ends
+    match (sweep_sphere_point(c, d, r, a), sweep_sphere_point(c, d, r, e)) {
+        (Some(t1), Some(t2)) => Some(t1.min(t2)),
+        (t1, t2) => t1.or(t2),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const R: f32 = 1.;
+
+    #[test]
+    fn fast_fall_lands_on_thin_slab() {
+        let slab = BoundingBox::new(-5., 5., 0., 0.1, -5., 5.);
+        let colliders = Colliders::new(vec![slab.into()]);
+        let mut pos = Vec3::new(0., 20., 0.);
+        let mut vel = Vec3::new(0., -2000., 0.);
+        // a single tick moves the ball far past the slab, so only a sweep stops it
+        let contacts = move_sphere(&mut pos, &mut vel, R, 1. / 60., &colliders);
+        assert!(contacts.grounded());
+        assert!((pos.y - 1.1).abs() < 1e-4, "stopped at {pos:?}");
+        assert_eq!(vel.y, 0.);
+    }
+
+    #[test]
+    fn diagonal_approach_stops_at_edge() {
+        // the edge along z at x = 1, y = 1
+        let block = BoundingBox::new(0., 1., 0., 1., -5., 5.);
+        let colliders = Colliders::new(vec![block.into()]);
+        let mut pos = Vec3::new(4., 4., 0.);
+        let mut vel = Vec3::new(-12., -12., 0.);
+        let contacts = move_sphere(&mut pos, &mut vel, R, 1., &colliders);
+        assert!(contacts.touching(Collider::Level(0)));
+        let edge = Vec3::new(1., 1., pos.z);
+        assert!(((pos - edge).mag() - R).abs() < 1e-4, "stopped at {pos:?}");
+        assert!(pos.x > 1. && pos.y > 1., "went through to {pos:?}");
+    }
+
+    #[test]
+    fn diagonal_approach_stops_at_corner() {
+        let block = BoundingBox::new(0., 1., 0., 1., 0., 1.);
+        let colliders = Colliders::new(vec![block.into()]);
+        let mut pos = Vec3::new(4., 4., 4.);
+        let mut vel = Vec3::new(-12., -12., -12.);
+        move_sphere(&mut pos, &mut vel, R, 1., &colliders);
+        assert!(((pos - block.max()).mag() - R).abs() < 1e-4, "stopped at {pos:?}");
+        assert!(pos.x > 1. && pos.y > 1. && pos.z > 1., "went through to {pos:?}");
+    }
+}
@@ -0,0 +1,200 @@
+use super::BoundingBox;
+use frenderer::types::*;
+
+// boxes per leaf before we stop splitting
+const LEAF_SIZE: usize = 4;
+
+enum NodeKind {
+    Leaf { start: usize, count: usize },
+    Inner { left: usize, right: usize },
+}
+
+struct Node {
+    bounds: BoundingBox,
+    kind: NodeKind,
+}
+
+// Bounding volume hierarchy over a fixed list of boxes. Queries hand back the
+// indices of boxes whose bounds are hit, the exact tests happen elsewhere.
+pub struct Bvh {
+    nodes: Vec<Node>,
+    indices: Vec<usize>,
+    bounds: Vec<BoundingBox>,
+}
+
+impl Bvh {
+    pub fn build(bounds: &[BoundingBox]) -> Self {
+        let mut bvh = Self {
+            nodes: vec![],
+            indices: (0..bounds.len()).collect(),
+            bounds: bounds.to_vec(),
+        };
+        if !bounds.is_empty() {
+            bvh.build_node(bounds, 0, bounds.len());
+        }
+        bvh
+    }
+
+    fn build_node(&mut self, bounds: &[BoundingBox], start: usize, count: usize) -> usize {
+        let node_bounds = self.indices[start..start + count]
+            .iter()
+            .map(|&i| bounds[i])
+            .reduce(|a, b| a.union(&b))
+            .unwrap();
+        let node_i = self.nodes.len();
+        self.nodes.push(Node {
+            bounds: node_bounds,
+            kind: NodeKind::Leaf { start, count },
+        });
+
+        if count <= LEAF_SIZE {
+            return node_i;
+        }
+
+        // split at the median along the axis the box centers are most spread out on
+        let (min, max) = self.indices[start..start + count]
+            .iter()
+            .map(|&i| bounds[i].center())
+            .fold((Vec3::broadcast(f32::MAX), Vec3::broadcast(f32::MIN)), |(min, max), c| {
+                (min.min_by_component(c), max.max_by_component(c))
+            });
+        let spread = max - min;
+        let axis = if spread.x > spread.y && spread.x > spread.z {
+            0
+        } else if spread.y > spread.z {
+            1
+        } else {
+            2
+        };
+        self.indices[start..start + count].sort_by(|&a, &b| {
+            bounds[a].center()[axis]
+                .partial_cmp(&bounds[b].center()[axis])
+                .unwrap()
+        });
+
+        let half = count / 2;
+        let left = self.build_node(bounds, start, half);
+        let right = self.build_node(bounds, start + half, count - half);
+        self.nodes[node_i].kind = NodeKind::Inner { left, right };
+        node_i
+    }
+
+    // Calls `visit` with every box index whose bounds, and whose nodes' bounds,
+    // pass `test`.
+    fn walk(&self, test: impl Fn(&BoundingBox) -> bool, mut visit: impl FnMut(usize)) {
+        if self.nodes.is_empty() {
+            return;
+        }
+        let mut stack = vec![0];
+        while let Some(node_i) = stack.pop() {
+            let node = &self.nodes[node_i];
+            if !test(&node.bounds) {
+                continue;
+            }
+            match node.kind {
+                NodeKind::Leaf { start, count } => {
+                    for &i in &self.indices[start..start + count] {
+                        if test(&self.bounds[i]) {
+                            visit(i);
+                        }
+                    }
+                }
+                NodeKind::Inner { left, right } => {
+                    stack.push(left);
+                    stack.push(right);
+                }
+            }
+        }
+    }
+
+    pub fn query_box(&self, b: &BoundingBox, out: &mut Vec<usize>) {
+        self.walk(|n| n.overlaps(b), |i| out.push(i));
+    }
+
+    pub fn query_sphere(&self, pos: Vec3, r: f32, out: &mut Vec<usize>) {
+        self.walk(|n| (n.closest_point(pos) - pos).mag_sq() <= r * r, |i| out.push(i));
+    }
+
+    // Boxes whose bounds the segment from `origin` to `origin + d` passes through.
+    pub fn query_ray(&self, origin: Vec3, d: Vec3, out: &mut Vec<usize>) {
+        self.walk(|n| super::ray_box(origin, d, n).is_some(), |i| out.push(i));
+    }
+
+    // Boxes a sphere of radius `r` might touch while moving by `motion`.
+    pub fn query_sweep(&self, pos: Vec3, motion: Vec3, r: f32, out: &mut Vec<usize>) {
+        self.walk(|n| super::ray_box(pos, motion, &n.expanded(r)).is_some(), |i| out.push(i));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::physics::ray_box;
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    fn random_boxes(rng: &mut StdRng, count: usize) -> Vec<BoundingBox> {
+        (0..count)
+            .map(|_| {
+                let center = Vec3::new(rng.gen_range(-20.0..20.), rng.gen_range(-20.0..20.), rng.gen_range(-20.0..20.));
+                let half = Vec3::new(rng.gen_range(0.1..3.), rng.gen_range(0.1..3.), rng.gen_range(0.1..3.));
+                BoundingBox::around(center, half)
+            })
+            .collect()
+    }
+
+    fn random_point(rng: &mut StdRng) -> Vec3 {
+        Vec3::new(rng.gen_range(-25.0..25.), rng.gen_range(-25.0..25.), rng.gen_range(-25.0..25.))
+    }
+
+    // the indices `query` finds, sorted, next to the ones passing `test` box by box
+    fn both_ways(
+        boxes: &[BoundingBox],
+        query: impl Fn(&mut Vec<usize>),
+        test: impl Fn(&BoundingBox) -> bool,
+    ) -> (Vec<usize>, Vec<usize>) {
+        let mut found = vec![];
+        query(&mut found);
+        found.sort_unstable();
+        let scanned = (0..boxes.len()).filter(|&i| test(&boxes[i])).collect();
+        (found, scanned)
+    }
+
+    #[test]
+    fn queries_match_a_linear_scan() {
+        let mut rng = StdRng::seed_from_u64(3);
+        for count in [0, 1, LEAF_SIZE, LEAF_SIZE + 1, 2 * LEAF_SIZE, 2 * LEAF_SIZE + 1, 100] {
+            let boxes = random_boxes(&mut rng, count);
+            let bvh = Bvh::build(&boxes);
+            for _ in 0..200 {
+                let (a, b) = (random_point(&mut rng), random_point(&mut rng));
+                let r = rng.gen_range(0.1..4.);
+
+                let probe = BoundingBox::around(a, Vec3::broadcast(r));
+                let (found, scanned) = both_ways(&boxes, |out| bvh.query_box(&probe, out), |bb| bb.overlaps(&probe));
+                assert_eq!(found, scanned, "{count} boxes, box {probe:?}");
+
+                let (found, scanned) = both_ways(
+                    &boxes,
+                    |out| bvh.query_sphere(a, r, out),
+                    |bb| (bb.closest_point(a) - a).mag_sq() <= r * r,
+                );
+                assert_eq!(found, scanned, "{count} boxes, sphere at {a:?} radius {r}");
+
+                let (found, scanned) = both_ways(
+                    &boxes,
+                    |out| bvh.query_ray(a, b - a, out),
+                    |bb| ray_box(a, b - a, bb).is_some(),
+                );
+                assert_eq!(found, scanned, "{count} boxes, ray from {a:?} to {b:?}");
+
+                let (found, scanned) = both_ways(
+                    &boxes,
+                    |out| bvh.query_sweep(a, b - a, r, out),
+                    |bb| ray_box(a, b - a, &bb.expanded(r)).is_some(),
+                );
+                assert_eq!(found, scanned, "{count} boxes, sweep from {a:?} to {b:?} radius {r}");
+            }
+        }
+    }
+}
@@ -0,0 +1,146 @@
+use frenderer::types::*;
+
+// below this speed into a surface nothing bounces, so resting contacts stay put
+const MIN_BOUNCE_SPEED: f32 = 2.;
+
+// How a surface responds to the ball touching it.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Material {
+    pub kind: MaterialKind,
+    // 1 is ordinary ground. Scales rolling traction and friction on the floor
+    // and how hard walls and ceilings grab the ball as it scrapes along them.
+    pub friction: f32,
+    // fraction of the speed into the surface that comes back out of it
+    pub restitution: f32,
+    // velocity of the surface itself, e.g. a conveyor belt
+    pub surface_velocity: Vec3,
+}
+
+// Named so rendering can tint or texture surfaces by what they are.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum MaterialKind {
+    Normal,
+    Ice,
+    Bouncy,
+    Sticky,
+    Conveyor,
+    Custom,
+}
+
+impl Default for Material {
+    fn default() -> Self {
+        Self {
+            kind: MaterialKind::Normal,
+            friction: 1.,
+            restitution: 0.,
+            surface_velocity: Vec3::zero(),
+        }
+    }
+}
+
+impl Material {
+    // Reads the words after `mat` on a level file line:
+    //   ice | bouncy | sticky | conveyor vx vy vz | custom friction restitution [vx vy vz]
+    pub fn parse(words: &[&str]) -> Result<Self, Box<dyn std::error::Error>> {
+        let cast = words
+            .iter()
+            .skip(1)
+            .map(|s| match s.parse::<f32>() {
+                Ok(x) if x.is_finite() => Ok(x),
+                Ok(_) => Err(format!("{s:?} isn't a finite number")),
+                Err(e) => Err(format!("{s:?}: {e}")),
+            })
+            .collect::<Result<Vec<f32>, _>>()?;
+        let vec_at = |i: usize| -> Result<Vec3, Box<dyn std::error::Error>> {
+            match cast.get(i..i + 3) {
+                Some(v) => Ok(Vec3::new(v[0], v[1], v[2])),
+                None => Err("expected a velocity x y z".into()),
+            }
+        };
+        let base = Self::default();
+
+        let material = match words.first().copied() {
+            Some("normal") => base,
+            Some("ice") => Self {
+                kind: MaterialKind::Ice,
+                friction: 0.1,
+                ..base
+            },
+            Some("bouncy") => Self {
+                kind: MaterialKind::Bouncy,
+                restitution: 0.9,
+                ..base
+            },
+            Some("sticky") => Self {
+                kind: MaterialKind::Sticky,
+                friction: 6.,
+                ..base
+            },
+            Some("conveyor") => Self {
+                kind: MaterialKind::Conveyor,
+                surface_velocity: vec_at(0)?,
+                ..base
+            },
+            Some("custom") => Self {
+                kind: MaterialKind::Custom,
+                friction: *cast.first().ok_or("expected friction")?,
+                restitution: *cast.get(1).ok_or("expected restitution")?,
+                surface_velocity: if cast.len() > 2 { vec_at(2)? } else { Vec3::zero() },
+            },
+            Some(other) => return Err(format!("unknown material {other:?}").into()),
+            None => return Err("expected a material name".into()),
+        };
+
+        Ok(material)
+    }
+
+    // The words `parse` would read back into this material, or None for
+    // plain ground which needs no `mat` at all.
+    pub fn file_words(&self) -> Option<String> {
+        let v = self.surface_velocity;
+        match self.kind {
+            MaterialKind::Normal => None,
+            MaterialKind::Ice => Some("ice".to_string()),
+            MaterialKind::Bouncy => Some("bouncy".to_string()),
+            MaterialKind::Sticky => Some("sticky".to_string()),
+            MaterialKind::Conveyor => Some(format!("conveyor {} {} {}", v.x, v.y, v.z)),
+            MaterialKind::Custom => Some(format!(
+                "custom {} {} {} {} {}",
+                self.friction, self.restitution, v.x, v.y, v.z
+            )),
+        }
+    }
+
+    // Velocity after touching a surface with normal `n` (pointing at the ball).
+    // The part going into the surface is removed or bounced back, and on walls
+    // and ceilings the tangential part loses up to `friction` times that much
+    // speed. On the floor the ball rolls instead of scraping, so floor friction
+    // is left to whoever drives the rolling.
+    pub fn respond(&self, v: Vec3, n: Vec3) -> Vec3 {
+        let friction = if n.y >= super::GROUND_NORMAL_Y { 0. } else { self.friction };
+        let rel = v - self.surface_velocity;
+        let into = rel.dot(n).min(0.);
+        let tangent = rel - n * rel.dot(n);
+
+        let bounce = if -into > MIN_BOUNCE_SPEED { self.restitution } else { 0. };
+        let normal_part = n * (rel.dot(n) - into * (1. + bounce));
+
+        let speed = tangent.mag();
+        let grip = (-into * friction).min(speed);
+        let tangent = if speed > 0. { tangent * ((speed - grip) / speed) } else { tangent };
+
+        normal_part + tangent + self.surface_velocity
+    }
+
+    // Debug color for drawing collision shapes.
+    pub fn color(&self) -> Vec4 {
+        match self.kind {
+            MaterialKind::Normal => Vec4::new(0.8, 0.8, 0.8, 1.),
+            MaterialKind::Ice => Vec4::new(0.6, 0.9, 1., 1.),
+            MaterialKind::Bouncy => Vec4::new(1., 0.4, 0.8, 1.),
+            MaterialKind::Sticky => Vec4::new(0.5, 0.9, 0.2, 1.),
+            MaterialKind::Conveyor => Vec4::new(1., 0.7, 0.1, 1.),
+            MaterialKind::Custom => Vec4::new(0.7, 0.5, 1., 1.),
+        }
+    }
+}
@@ -0,0 +1,742 @@
+use super::{BoundingBox, Material};
+use crate::files::write_atomically;
+use crate::parse::{data_lines, LineCursor, ParseError, Word};
+use frenderer::types::*;
+
+// conservative advancement gives up after this many steps
+const MAX_ADVANCE_STEPS: usize = 32;
+const ADVANCE_EPSILON: f32 = 1e-4;
+// straight pieces a cylinder's rims are drawn with
+const CYLINDER_SEGMENTS: usize = 16;
+
+// Box with its own rotation, given by its center and half extents.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Obb {
+    pub center: Vec3,
+    pub half: Vec3,
+    pub rot: Rotor3,
+}
+
+// Ramp filling the lower half of an oriented box, cut diagonally so that it
+// is flush with the bottom at local -x and full height at local +x.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Wedge {
+    pub center: Vec3,
+    pub half: Vec3,
+    pub rot: Rotor3,
+}
+
+// Upright cylinder, like a pillar.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Cylinder {
+    pub center: Vec3,
+    pub r: f32,
+    pub half_height: f32,
+}
+
+// Single triangle of a render mesh. It has no inside, so it pushes spheres
+// back out whichever side they came from.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Triangle {
+    pub a: Vec3,
+    pub b: Vec3,
+    pub c: Vec3,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Shape {
+    Aabb(BoundingBox),
+    Obb(Obb),
+    Wedge(Wedge),
+    Cylinder(Cylinder),
+    Triangle(Triangle),
+}
+
+impl From<BoundingBox> for Shape {
+    fn from(b: BoundingBox) -> Self {
+        Shape::Aabb(b)
+    }
+}
+
+impl Shape {
+    // One shape per line:
+    //   min_x max_x min_y max_y min_z max_z               axis-aligned box
+    //   obb cx cy cz hx hy hz yaw [pitch roll]            rotated box, degrees
+    //   wedge cx cy cz hx hy hz yaw [pitch roll]          ramp rising toward local +x
+    //   cylinder x z min_y max_y r                        upright cylinder
+    // Any line can end with `mat <name> [args]` to give it a material, see
+    // `Material::parse`; without one the surface is plain ground. Blank lines
+    // and anything after a `#` are ignored.
+    pub fn from_file(filepath: &str) -> Result<Vec<(Self, Material)>, Box<dyn std::error::Error>> {
+        let mut shapes: Vec<(Self, Material)> = vec![];
+        for (number, text) in data_lines(filepath)? {
+            shapes.push(Self::parse_line(&mut LineCursor::new(filepath, number, &text))?);
+        }
+        Ok(shapes)
+    }
+
+    fn parse_line(line: &mut LineCursor) -> Result<(Self, Material), ParseError> {
+        let kind = match line.peek() {
+            Some(w) if w.text.parse::<f32>().is_ok() => Word { text: "box", ..w },
+            _ => line.word("a shape")?,
+        };
+        let shape = match kind.text {
+            "box" => {
+                let mut bounds = [0.; 6];
+                for axis in 0..3 {
+                    let column = line.column();
+                    let min = line.float("a minimum")?;
+                    let max = line.float("a maximum")?;
+                    if min > max {
+                        let name = ["x", "y", "z"][axis];
+                        return Err(line.error(column, format!("box min {name} {min} is above max {name} {max}")));
+                    }
+                    bounds[axis * 2] = min;
+                    bounds[axis * 2 + 1] = max;
+                }
+                Shape::Aabb(BoundingBox::new(
+                    bounds[0], bounds[1], bounds[2], bounds[3], bounds[4], bounds[5],
+                ))
+            }
+            "obb" | "wedge" => {
+                let center = Vec3::new(line.float("center x")?, line.float("center y")?, line.float("center z")?);
+                let column = line.column();
+                let half = Vec3::new(line.float("half x")?, line.float("half y")?, line.float("half z")?);
+                if half.component_min() <= 0. {
+                    return Err(line.error(column, "half extents must be positive"));
+                }
+                let mut angles = vec![line.float("a yaw angle")?];
+                if let Some(pitch) = line.optional_float("a pitch angle")? {
+                    angles.push(pitch);
+                    angles.push(line.float("a roll angle")?);
+                }
+                let rot = rotation_from(&angles);
+                if kind.text == "obb" {
+                    Shape::Obb(Obb { center, half, rot })
+                } else {
+                    Shape::Wedge(Wedge { center, half, rot })
+                }
+            }
+            "cylinder" => {
+                let (x, z) = (line.float("x")?, line.float("z")?);
+                let column = line.column();
+                let (min_y, max_y) = (line.float("min y")?, line.float("max y")?);
+                if min_y > max_y {
+                    return Err(line.error(column, format!("cylinder min y {min_y} is above max y {max_y}")));
+                }
+                let column = line.column();
+                let r = line.float("a radius")?;
+                if r <= 0. {
+                    return Err(line.error(column, "cylinder radius must be positive"));
+                }
+                Shape::Cylinder(Cylinder {
+                    center: Vec3::new(x, (min_y + max_y) / 2., z),
+                    r,
+                    half_height: (max_y - min_y) / 2.,
+                })
+            }
+            other => return Err(line.error(kind.column, format!("unknown shape {other:?}"))),
+        };
+        let material = match line.peek() {
+            Some(w) if w.text == "mat" => {
+                line.word("mat")?;
+                let column = line.column();
+                let words: Vec<&str> = line.rest().iter().map(|w| w.text).collect();
+                Material::parse(&words).map_err(|e| line.error(column, e.to_string()))?
+            }
+            _ => Material::default(),
+        };
+        line.finish()?;
+        Ok((shape, material))
+    }
+
+    // Writes shapes back out in the format `from_file` reads. Triangles come
+    // from meshes rather than shape files, so they can't be saved.
+    pub fn to_file(filepath: &str, shapes: &[(Self, Material)]) -> Result<(), Box<dyn std::error::Error>> {
+        let mut text = String::new();
+        for (shape, material) in shapes {
+            let line = shape
+                .file_line()
+                .ok_or_else(|| format!("{filepath}: can't save a {shape:?}"))?;
+            match material.file_words() {
+                Some(words) => text += &format!("{line} mat {words}\n"),
+                None => text += &format!("{line}\n"),
+            }
+        }
+        write_atomically(std::path::Path::new(filepath), &text)
+    }
+
+    fn file_line(&self) -> Option<String> {
+        let line = match self {
+            Shape::Aabb(b) => format!(
+                "{} {} {} {} {} {}",
+                b.min_x, b.max_x, b.min_y, b.max_y, b.min_z, b.max_z
+            ),
+            Shape::Obb(Obb { center: c, half: h, rot }) | Shape::Wedge(Wedge { center: c, half: h, rot }) => {
+                let kind = if matches!(self, Shape::Obb(_)) { "obb" } else { "wedge" };
+                let [yaw, pitch, roll] = angles_of(*rot);
+                format!("{kind} {} {} {} {} {} {} {yaw} {pitch} {roll}", c.x, c.y, c.z, h.x, h.y, h.z)
+            }
+            Shape::Cylinder(c) => format!(
+                "cylinder {} {} {} {} {}",
+                c.center.x,
+                c.center.z,
+                c.center.y - c.half_height,
+                c.center.y + c.half_height,
+                c.r
+            ),
+            Shape::Triangle(_) => return None,
+        };
+        Some(line)
+    }
+
+    pub fn translate(&mut self, d: Vec3) {
+        match self {
+            Shape::Aabb(b) => *b = BoundingBox::around(b.center() + d, (b.max() - b.min()) / 2.),
+            Shape::Obb(Obb { center, .. }) | Shape::Wedge(Wedge { center, .. }) => *center += d,
+            Shape::Cylinder(c) => c.center += d,
+            Shape::Triangle(t) => {
+                t.a += d;
+                t.b += d;
+                t.c += d;
+            }
+        }
+    }
+
+    // Grows the shape by `by`, half on each side, keeping every half size at
+    // least `min_half`. Rotated shapes grow along their own axes and
+    // cylinders take x and z as changes to their diameter. Triangles have no
+    // size of their own, so they stay as they are and this returns false.
+    pub fn resize(&mut self, by: Vec3, min_half: f32) -> bool {
+        let grow = |half: Vec3| (half + by / 2.).max_by_component(Vec3::broadcast(min_half));
+        match self {
+            Shape::Aabb(b) => *b = BoundingBox::around(b.center(), grow((b.max() - b.min()) / 2.)),
+            Shape::Obb(Obb { half, .. }) | Shape::Wedge(Wedge { half, .. }) => *half = grow(*half),
+            Shape::Cylinder(c) => {
+                c.r = (c.r + (by.x + by.z) / 2.).max(min_half);
+                c.half_height = (c.half_height + by.y / 2.).max(min_half);
+            }
+            Shape::Triangle(_) => return false,
+        }
+        true
+    }
+
+    // The shape's outline as line segments, for drawing it as a wireframe.
+    pub fn edges(&self) -> Vec<(Vec3, Vec3)> {
+        match self {
+            Shape::Aabb(b) => box_edges(b.center(), (b.max() - b.min()) / 2., Rotor3::identity()),
+            Shape::Obb(o) => box_edges(o.center, o.half, o.rot),
+            Shape::Wedge(w) => {
+                let tri = w.triangle();
+                let corner = |i: usize, z: f32| w.center + w.rot * Vec3::new(tri[i].x, tri[i].y, z);
+                let (front, back) = (w.half.z, -w.half.z);
+                (0..3)
+                    .flat_map(|i| {
+                        let j = (i + 1) % 3;
+                        [
+                            (corner(i, front), corner(j, front)),
+                            (corner(i, back), corner(j, back)),
+                            (corner(i, front), corner(i, back)),
+                        ]
+                    })
+                    .collect()
+            }
+            Shape::Cylinder(c) => {
+                let rim = |k: usize, y: f32| {
+                    let angle = k as f32 / CYLINDER_SEGMENTS as f32 * 2. * PI;
+                    c.center + Vec3::new(angle.cos() * c.r, y, angle.sin() * c.r)
+                };
+                let (top, bottom) = (c.half_height, -c.half_height);
+                let rims = (0..CYLINDER_SEGMENTS)
+                    .flat_map(|k| [(rim(k, top), rim(k + 1, top)), (rim(k, bottom), rim(k + 1, bottom))]);
+                // and a few lines down the side
+                let sides = (0..CYLINDER_SEGMENTS)
+                    .step_by(CYLINDER_SEGMENTS / 4)
+                    .map(|k| (rim(k, top), rim(k, bottom)));
+                rims.chain(sides).collect()
+            }
+            Shape::Triangle(t) => vec![(t.a, t.b), (t.b, t.c), (t.c, t.a)],
+        }
+    }
+
+    // Triangles for every face of an indexed triangle list, dropping any
+    // that have no area.
+    pub fn triangles(verts: &[Vec3], indices: &[u32]) -> Vec<Self> {
+        indices
+            .chunks_exact(3)
+            .map(|f| Triangle {
+                a: verts[f[0] as usize],
+                b: verts[f[1] as usize],
+                c: verts[f[2] as usize],
+            })
+            .filter(|t| (t.b - t.a).cross(t.c - t.a).mag_sq() > 0.)
+            .map(Shape::Triangle)
+            .collect()
+    }
+
+    pub fn bounds(&self) -> BoundingBox {
+        match self {
+            Shape::Aabb(b) => *b,
+            Shape::Obb(Obb { center, half, rot }) | Shape::Wedge(Wedge { center, half, rot }) => {
+                // project the rotated axes onto the world axes
+                let extent = (0..3)
+                    .map(|i| {
+                        let mut axis = Vec3::zero();
+                        axis[i] = half[i];
+                        let axis = *rot * axis;
+                        Vec3::new(axis.x.abs(), axis.y.abs(), axis.z.abs())
+                    })
+                    .fold(Vec3::zero(), |a, b| a + b);
+                BoundingBox::around(*center, extent)
+            }
+            Shape::Cylinder(c) => {
+                BoundingBox::around(c.center, Vec3::new(c.r, c.half_height, c.r))
+            }
+            Shape::Triangle(t) => {
+                let min = t.a.min_by_component(t.b).min_by_component(t.c);
+                let max = t.a.max_by_component(t.b).max_by_component(t.c);
+                BoundingBox::new(min.x, max.x, min.y, max.y, min.z, max.z)
+            }
+        }
+    }
+
+    // Closest point on or in the shape, which is `p` itself when it's inside.
+    pub fn closest_point(&self, p: Vec3) -> Vec3 {
+        match self {
+            Shape::Aabb(b) => b.closest_point(p),
+            Shape::Obb(o) => {
+                let local = o.rot.reversed() * (p - o.center);
+                o.center + o.rot * clamp_box(local, o.half)
+            }
+            Shape::Wedge(w) => {
+                let local = w.rot.reversed() * (p - w.center);
+                w.center + w.rot * w.closest_local(local)
+            }
+            Shape::Cylinder(c) => c.center + c.closest_local(p - c.center),
+            Shape::Triangle(t) => t.closest_point(p),
+        }
+    }
+
+    // Signed distance from `p` to the surface (negative inside), with the
+    // nearest surface point and the outward normal there. Everything is
+    // worked out in the shape's own frame so rotating back and forth can't
+    // blur inside and outside. `motion` breaks ties for points inside a box
+    // by skipping faces the motion is heading away from.
+    pub fn query(&self, p: Vec3, motion: Vec3) -> (f32, Vec3, Vec3) {
+        match self {
+            Shape::Aabb(b) => {
+                let center = b.center();
+                let (d, point, normal) = query_box(p - center, (b.max() - b.min()) * 0.5, motion);
+                (d, center + point, normal)
+            }
+            Shape::Obb(o) => {
+                let inv = o.rot.reversed();
+                let (d, point, normal) = query_box(inv * (p - o.center), o.half, inv * motion);
+                (d, o.center + o.rot * point, o.rot * normal)
+            }
+            Shape::Wedge(w) => {
+                let local = w.rot.reversed() * (p - w.center);
+                let (d, point, normal) = outside_or(local, w.closest_local(local), || w.exit_local(local));
+                (d, w.center + w.rot * point, w.rot * normal)
+            }
+            Shape::Cylinder(c) => {
+                let local = p - c.center;
+                let (d, point, normal) = outside_or(local, c.closest_local(local), || c.exit_local(local));
+                (d, c.center + point, normal)
+            }
+            Shape::Triangle(t) => {
+                let point = t.closest_point(p);
+                let offset = p - point;
+                if offset.mag_sq() > 0. {
+                    (offset.mag(), point, offset.normalized())
+                } else {
+                    // right on the face, so face back against the motion
+                    let n = (t.b - t.a).cross(t.c - t.a).normalized();
+                    (0., point, if n.dot(motion) > 0. { -n } else { n })
+                }
+            }
+        }
+    }
+
+    pub fn distance(&self, p: Vec3) -> f32 {
+        self.query(p, Vec3::zero()).0
+    }
+
+    pub fn surface(&self, p: Vec3, motion: Vec3) -> (Vec3, Vec3) {
+        let (_, point, normal) = self.query(p, motion);
+        (point, normal)
+    }
+
+    pub fn touching_sphere(&self, pos: Vec3, r: f32) -> bool {
+        self.distance(pos) <= r
+    }
+
+    // Earliest fraction of `motion` (0 to 1) at which a sphere starting at
+    // `c` touches the shape, or None if it never does during this move.
+    pub fn sweep_sphere(&self, c: Vec3, motion: Vec3, r: f32) -> Option<f32> {
+        match self {
+            Shape::Aabb(b) => super::sweep_sphere_box(c, motion, r, b),
+            Shape::Obb(o) => {
+                // boxes are exact in their own frame
+                let inv = o.rot.reversed();
+                let local = BoundingBox::around(Vec3::zero(), o.half);
+                super::sweep_sphere_box(inv * (c - o.center), inv * motion, r, &local)
+            }
+            _ => self.advance(c, motion, r),
+        }
+    }
+
+    // First fraction of `d` at which the segment from `c` hits the shape.
+    pub fn ray(&self, c: Vec3, d: Vec3) -> Option<f32> {
+        match self {
+            Shape::Aabb(b) => super::ray_box(c, d, b),
+            Shape::Obb(o) => {
+                let inv = o.rot.reversed();
+                let local = BoundingBox::around(Vec3::zero(), o.half);
+                super::ray_box(inv * (c - o.center), inv * d, &local)
+            }
+            _ => self.advance(c, d, 0.),
+        }
+    }
+
+    // Conservative advancement: step forward by the current gap, which can
+    // never overshoot a convex shape.
+    fn advance(&self, c: Vec3, motion: Vec3, r: f32) -> Option<f32> {
+        let (d, _, normal) = self.query(c, motion);
+        if d <= r {
+            return if normal.dot(motion) < -1e-6 || d <= 0. { Some(0.) } else { None };
+        }
+
+        let len = motion.mag();
+        if len == 0. {
+            return None;
+        }
+        let mut t = 0.;
+        for _ in 0..MAX_ADVANCE_STEPS {
+            let gap = self.distance(c + motion * t) - r;
+            if gap < ADVANCE_EPSILON {
+                return Some(t);
+            }
+            t += gap / len;
+            if t > 1. {
+                return None;
+            }
+        }
+        // a glancing approach can use up the steps before the gap closes; the
+        // time reached so far is still safe to stop at, ignoring it isn't
+        Some(t)
+    }
+}
+
+impl Wedge {
+    // the ramp's cross-section in local x/y
+    fn triangle(&self) -> [Vec2; 3] {
+        let h = self.half;
+        [Vec2::new(-h.x, -h.y), Vec2::new(h.x, -h.y), Vec2::new(h.x, h.y)]
+    }
+
+    fn closest_local(&self, p: Vec3) -> Vec3 {
+        // a prism is a triangle times an interval, so each part clamps on its own
+        let q = closest_on_triangle(Vec2::new(p.x, p.y), self.triangle());
+        Vec3::new(q.x, q.y, p.z.clamp(-self.half.z, self.half.z))
+    }
+
+    fn exit_local(&self, p: Vec3) -> (f32, Vec3, Vec3) {
+        let tri = self.triangle();
+        let q = Vec2::new(p.x, p.y);
+        let mut best = (
+            self.half.z - p.z.abs(),
+            Vec3::new(p.x, p.y, self.half.z.copysign(p.z)),
+            Vec3::new(0., 0., 1f32.copysign(p.z)),
+        );
+        for i in 0..3 {
+            let (a, b) = (tri[i], tri[(i + 1) % 3]);
+            let edge = b - a;
+            // the triangle winds counterclockwise, so outward is to the right
+            let n = Vec2::new(edge.y, -edge.x).normalized();
+            let d = (a - q).dot(n);
+            if d < best.0 {
+                let on = q + n * d;
+                best = (d, Vec3::new(on.x, on.y, p.z), Vec3::new(n.x, n.y, 0.));
+            }
+        }
+        (-best.0, best.1, best.2)
+    }
+}
+
+impl Cylinder {
+    fn closest_local(&self, p: Vec3) -> Vec3 {
+        let radial = Vec2::new(p.x, p.z);
+        let radial = if radial.mag() > self.r {
+            radial.normalized() * self.r
+        } else {
+            radial
+        };
+        Vec3::new(radial.x, p.y.clamp(-self.half_height, self.half_height), radial.y)
+    }
+
+    fn exit_local(&self, p: Vec3) -> (f32, Vec3, Vec3) {
+        let radial = Vec2::new(p.x, p.z);
+        let side = self.r - radial.mag();
+        let cap = self.half_height - p.y.abs();
+        if cap < side {
+            let y = self.half_height.copysign(p.y);
+            (-cap, Vec3::new(p.x, y, p.z), Vec3::new(0., 1f32.copysign(p.y), 0.))
+        } else {
+            let dir = if radial.mag_sq() > 0. {
+                radial.normalized()
+            } else {
+                Vec2::unit_x()
+            };
+            (
+                -side,
+                Vec3::new(dir.x * self.r, p.y, dir.y * self.r),
+                Vec3::new(dir.x, 0., dir.y),
+            )
+        }
+    }
+}
+
+// yaw, then optional pitch and roll, in degrees
+fn rotation_from(angles: &[f32]) -> Rotor3 {
+    let angle = |i: usize| angles.get(i).copied().unwrap_or(0.).to_radians();
+    Rotor3::from_euler_angles(angle(2), angle(1), angle(0))
+}
+
+// The inverse of `rotation_from`: [yaw, pitch, roll] in degrees. Yaw and
+// pitch come from where local +z ends up, and roll is whatever's left.
+fn angles_of(rot: Rotor3) -> [f32; 3] {
+    let z = rot * Vec3::unit_z();
+    let pitch = (-z.y).clamp(-1., 1.).asin();
+    let yaw = (-z.x).atan2(z.z);
+    let x = (Rotor3::from_euler_angles(0., pitch, yaw).reversed() * rot) * Vec3::unit_x();
+    let roll = x.y.atan2(x.x);
+    [yaw.to_degrees(), pitch.to_degrees(), roll.to_degrees()]
+}
+
+impl Triangle {
+    // Ericson, Real-Time Collision Detection 5.1.5
+    pub fn closest_point(&self, p: Vec3) -> Vec3 {
+        let (a, b, c) = (self.a, self.b, self.c);
+        let ab = b - a;
+        let ac = c - a;
+        let ap = p - a;
+        let d1 = ab.dot(ap);
+        let d2 = ac.dot(ap);
+        if d1 <= 0. && d2 <= 0. {
+            return a;
+        }
+
+        let bp = p - b;
+        let d3 = ab.dot(bp);
+        let d4 = ac.dot(bp);
+        if d3 >= 0. && d4 <= d3 {
+            return b;
+        }
+
+        let vc = d1 * d4 - d3 * d2;
+        if vc <= 0. && d1 >= 0. && d3 <= 0. {
+            return a + ab * (d1 / (d1 - d3));
+        }
+
+        let cp = p - c;
+        let d5 = ab.dot(cp);
+        let d6 = ac.dot(cp);
+        if d6 >= 0. && d5 <= d6 {
+            return c;
+        }
+
+        let vb = d5 * d2 - d1 * d6;
+        if vb <= 0. && d2 >= 0. && d6 <= 0. {
+            return a + ac * (d2 / (d2 - d6));
+        }
+
+        let va = d3 * d6 - d5 * d4;
+        if va <= 0. && (d4 - d3) >= 0. && (d5 - d6) >= 0. {
+            return b + (c - b) * ((d4 - d3) / ((d4 - d3) + (d5 - d6)));
+        }
+
+        let denom = 1. / (va + vb + vc);
+        a + ab * (vb * denom) + ac * (vc * denom)
+    }
+}
+
+// The twelve edges of a box, as corners one bit apart.
+fn box_edges(center: Vec3, half: Vec3, rot: Rotor3) -> Vec<(Vec3, Vec3)> {
+    let corner = |i: usize| {
+        let sign = |bit: usize| if i & bit == 0 { -1. } else { 1. };
+        center + rot * (half * Vec3::new(sign(1), sign(2), sign(4)))
+    };
+    (0..8)
+        .flat_map(|i| [1, 2, 4].into_iter().filter(move |bit| i & bit == 0).map(move |bit| (i, i | bit)))
+        .map(|(a, b)| (corner(a), corner(b)))
+        .collect()
+}
+
+fn clamp_box(p: Vec3, half: Vec3) -> Vec3 {
+    p.clamped(-half, half)
+}
+
+fn outside_or(p: Vec3, closest: Vec3, exit: impl FnOnce() -> (f32, Vec3, Vec3)) -> (f32, Vec3, Vec3) {
+    let offset = p - closest;
+    if offset.mag_sq() > 0. {
+        (offset.mag(), closest, offset.normalized())
+    } else {
+        exit()
+    }
+}
+
+// Box centered on the origin. Points inside leave through the nearest face.
+fn query_box(p: Vec3, half: Vec3, motion: Vec3) -> (f32, Vec3, Vec3) {
+    outside_or(p, clamp_box(p, half), || {
+        let mut best = (f32::MAX, p, Vec3::unit_y());
+        for i in 0..3 {
+            for sign in [-1., 1.] {
+                let mut normal = Vec3::zero();
+                normal[i] = sign;
+                if normal.dot(motion) > 0. {
+                    continue;
+                }
+                let d = half[i] - p[i] * sign;
+                if d < best.0 {
+                    let mut point = p;
+                    point[i] = half[i] * sign;
+                    best = (d, point, normal);
+                }
+            }
+        }
+        (-best.0, best.1, best.2)
+    })
+}
+
+fn closest_on_segment(p: Vec2, a: Vec2, b: Vec2) -> Vec2 {
+    let ab = b - a;
+    if ab.mag_sq() == 0. {
+        return a;
+    }
+    let t = ((p - a).dot(ab) / ab.mag_sq()).clamp(0., 1.);
+    a + ab * t
+}
+
+fn closest_on_triangle(p: Vec2, tri: [Vec2; 3]) -> Vec2 {
+    // a flat triangle has no inside, only its edges
+    let (ab, ac) = (tri[1] - tri[0], tri[2] - tri[0]);
+    let inside = ab.x * ac.y - ab.y * ac.x > 0. && (0..3).all(|i| {
+        let (a, b) = (tri[i], tri[(i + 1) % 3]);
+        let edge = b - a;
+        edge.x * (p.y - a.y) - edge.y * (p.x - a.x) >= 0.
+    });
+    if inside {
+        return p;
+    }
+    (0..3)
+        .map(|i| closest_on_segment(p, tri[i], tri[(i + 1) % 3]))
+        .min_by(|a, b| (*a - p).mag_sq().partial_cmp(&(*b - p).mag_sq()).unwrap())
+        .unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(text: &str) -> Result<(Shape, Material), ParseError> {
+        Shape::parse_line(&mut LineCursor::new("test_bb.txt", 1, text))
+    }
+
+    #[test]
+    fn flat_boxes_and_wedges_are_rejected() {
+        let flat = [
+            ("wedge 0 0 0 0 1 1 0", 13),
+            ("obb 0 0 0 1 0 1 45", 11),
+            ("obb 0 0 0 1 1 -1 0", 11),
+        ];
+        for (text, column) in flat {
+            let e = parse(text).unwrap_err();
+            assert_eq!(e.column, column, "{text}: {e}");
+        }
+        assert!(parse("wedge 0 0 0 1 1 1 0").is_ok());
+    }
+
+    #[test]
+    fn numbers_that_arent_finite_are_rejected() {
+        for (text, column) in [("nan 1 0 1 0 1", 1), ("0 1 0 inf 0 1", 7), ("cylinder 0 0 0 1 NaN", 18)] {
+            let e = parse(text).unwrap_err();
+            assert_eq!(e.column, column, "{text}: {e}");
+        }
+        assert!(parse("0 1 0 1 0 1 mat custom nan 0").is_err());
+    }
+
+    // Sweeps a sphere of radius 1 that starts 1.5 from `shape` along `along`
+    // (a unit vector in the surface) while sinking 1 toward it. It touches
+    // halfway through the move, and shouldn't end up inside before that.
+    fn glancing_sweep(shape: Shape, start: Vec3, along: Vec3, normal: Vec3) {
+        let motion = along * 30. - normal;
+        let t = shape.sweep_sphere(start, motion, 1.).expect("missed a glancing hit");
+        assert!(t <= 0.5 + 1e-4, "{shape:?}: stopped at {t}, past the touch");
+        let gap = shape.distance(start + motion * t) - 1.;
+        assert!(gap > -1e-4, "{shape:?}: {gap} inside at {t}");
+    }
+
+    #[test]
+    fn glancing_sweep_hits_a_triangle() {
+        let up = Vec3::unit_y();
+        let verts = [Vec3::new(-50., 0., -50.), Vec3::new(50., 0., -50.), Vec3::new(0., 0., 50.)];
+        let floor = Shape::triangles(&verts, &[0, 2, 1]).remove(0);
+        glancing_sweep(floor, up * 1.5, Vec3::unit_x(), up);
+    }
+
+    #[test]
+    fn glancing_sweep_hits_a_wedge() {
+        let half = Vec3::new(20., 20., 10.);
+        let ramp = Shape::Wedge(Wedge { center: Vec3::zero(), half, rot: Rotor3::identity() });
+        let along = Vec3::new(1., 1., 0.).normalized();
+        let normal = Vec3::new(-1., 1., 0.).normalized();
+        glancing_sweep(ramp, normal * 1.5 - along * 14., along, normal);
+    }
+
+    #[test]
+    fn closest_on_flat_triangle() {
+        let p = Vec2::new(2., 1.);
+        let point = [Vec2::zero(); 3];
+        assert_eq!(closest_on_triangle(p, point), Vec2::zero());
+        let line = [Vec2::new(-1., 0.), Vec2::new(1., 0.), Vec2::new(1., 0.)];
+        assert_eq!(closest_on_triangle(p, line), Vec2::new(1., 0.));
+    }
+
+    #[test]
+    fn edges_lie_on_the_surface() {
+        let rot = rotation_from(&[30., 10., 5.]);
+        let (center, half) = (Vec3::new(1., 2., 3.), Vec3::new(2., 1., 0.5));
+        let shapes = [
+            (Shape::Obb(Obb { center, half, rot }), 12),
+            (Shape::Wedge(Wedge { center, half, rot }), 9),
+            (Shape::Cylinder(Cylinder { center, r: 2., half_height: 1. }), CYLINDER_SEGMENTS * 2 + 4),
+        ];
+        for (shape, count) in shapes {
+            let edges = shape.edges();
+            assert_eq!(edges.len(), count, "{shape:?}");
+            for (from, to) in edges {
+                for p in [from, to, (from + to) / 2.] {
+                    assert!(shape.distance(p).abs() < 1e-4, "{shape:?}: {p:?} is off the surface");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn resize_grows_along_the_shapes_own_axes() {
+        let rot = rotation_from(&[90.]);
+        let mut obb = Shape::Obb(Obb { center: Vec3::zero(), half: Vec3::one(), rot });
+        assert!(obb.resize(Vec3::new(1., 0., -4.), 0.25));
+        assert_eq!(obb, Shape::Obb(Obb { center: Vec3::zero(), half: Vec3::new(1.5, 1., 0.25), rot }));
+
+        let mut cylinder = Shape::Cylinder(Cylinder { center: Vec3::zero(), r: 1., half_height: 1. });
+        assert!(cylinder.resize(Vec3::new(0., 1., 1.), 0.25));
+        assert_eq!(cylinder, Shape::Cylinder(Cylinder { center: Vec3::zero(), r: 1.5, half_height: 1.5 }));
+
+        let tri = Triangle { a: Vec3::zero(), b: Vec3::unit_x(), c: Vec3::unit_z() };
+        let mut triangle = Shape::Triangle(tri);
+        assert!(!triangle.resize(Vec3::one(), 0.25));
+        assert_eq!(triangle, Shape::Triangle(tri));
+    }
+}
@@ -0,0 +1,277 @@
+use crate::parse::{data_lines, LineCursor, ParseError};
+use crate::physics::{BoundingBox, Shape};
+use frenderer::assets::Assets;
+use frenderer::renderer::textured::Model;
+use frenderer::types::*;
+use std::path::Path;
+use std::rc::Rc;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PathMode {
+    Loop,     // last waypoint goes back to the first
+    PingPong, // turn around at either end
+}
+
+// A point following a list of waypoints at a constant speed in units/second.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Route {
+    pub pos: Vec3,
+    pub path: Vec<Vec3>,
+    pub speed: f32,
+    pub mode: PathMode,
+    target: usize,
+    backwards: bool,
+}
+
+impl Route {
+    pub fn new(path: Vec<Vec3>, speed: f32, mode: PathMode) -> Self {
+        Self {
+            pos: path[0],
+            target: 1 % path.len(),
+            path,
+            speed,
+            mode,
+            backwards: false,
+        }
+    }
+
+    // Back to the first waypoint, heading for the second.
+    pub fn reset(&mut self) {
+        self.pos = self.path[0];
+        self.target = 1 % self.path.len();
+        self.backwards = false;
+    }
+
+    // Moves `dt` seconds along the path and returns how far it went.
+    pub fn step(&mut self, dt: f32) -> Vec3 {
+        let start = self.pos;
+        let mut left = self.speed * dt;
+
+        // every waypoint twice is enough to use up any speed on a sane path
+        for _ in 0..self.path.len() * 2 {
+            let to = self.path[self.target] - self.pos;
+            let dist = to.mag();
+            if dist > left {
+                self.pos += to * (left / dist);
+                break;
+            }
+            self.pos = self.path[self.target];
+            left -= dist;
+            self.next_target();
+        }
+
+        self.pos - start
+    }
+
+    fn next_target(&mut self) {
+        let last = self.path.len() - 1;
+        match self.mode {
+            PathMode::Loop => self.target = (self.target + 1) % self.path.len(),
+            PathMode::PingPong => {
+                if last == 0 {
+                    return;
+                }
+                if (self.backwards && self.target == 0) || (!self.backwards && self.target == last) {
+                    self.backwards = !self.backwards;
+                }
+                if self.backwards {
+                    self.target -= 1;
+                } else {
+                    self.target += 1;
+                }
+            }
+        }
+    }
+}
+
+// Box that follows a route, carrying whatever rides on it.
+pub struct Platform {
+    pub trf: Similarity3,
+    pub model: Rc<Model>,
+    pub half: Vec3,
+    pub route: Route,
+}
+
+impl Platform {
+    pub fn new(model: Rc<Model>, half: Vec3, path: Vec<Vec3>, speed: f32, mode: PathMode) -> Self {
+        let route = Route::new(path, speed, mode);
+        Self {
+            trf: Similarity3::new(route.pos, Rotor3::identity(), 1.),
+            model,
+            half,
+            route,
+        }
+    }
+
+    // Loads the model for each platform in a file, see `PlatformSpec::from_file`.
+    pub fn from_file(assets: &mut Assets, filepath: &str) -> Result<Vec<Self>, Box<dyn std::error::Error>> {
+        let mut platforms: Vec<Self> = vec![];
+
+        for spec in PlatformSpec::from_file(filepath)? {
+            let tex = assets.load_texture(Path::new(&format!("content/{}.png", spec.texture)))?;
+            let mesh = assets.load_textured(Path::new(&format!("content/{}.obj", spec.mesh)))?;
+            let l = mesh.len();
+            let model = assets.create_textured_model(mesh, vec![tex; l]);
+
+            platforms.push(Self::new(model, spec.half, spec.path, spec.speed, spec.mode));
+        }
+
+        Ok(platforms)
+    }
+
+    pub fn reset(&mut self) {
+        self.route.reset();
+        self.trf.translation = self.route.pos;
+    }
+
+    pub fn shape(&self) -> Shape {
+        Shape::Aabb(BoundingBox::around(self.trf.translation, self.half))
+    }
+
+    // Moves `dt` seconds along the route and returns how far the platform went.
+    pub fn step(&mut self, dt: f32) -> Vec3 {
+        let delta = self.route.step(dt);
+        self.trf.translation = self.route.pos;
+        delta
+    }
+}
+
+// A platform as written in a platforms file, before its model is loaded.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PlatformSpec {
+    pub mesh: String,
+    pub texture: String,
+    pub half: Vec3,
+    pub speed: f32,
+    pub mode: PathMode,
+    pub path: Vec<Vec3>,
+}
+
+impl PlatformSpec {
+    // One platform per line:
+    //   mesh texture hx hy hz speed loop|pingpong x y z x y z ...
+    // where mesh and texture are names in content/ and the platform starts
+    // at the first waypoint. Blank lines and `#` comments are ignored.
+    pub fn from_file(filepath: &str) -> Result<Vec<Self>, Box<dyn std::error::Error>> {
+        let mut specs: Vec<Self> = vec![];
+
+        for (number, text) in data_lines(filepath)? {
+            specs.push(Self::parse_line(&mut LineCursor::new(filepath, number, &text))?);
+        }
+
+        Ok(specs)
+    }
+
+    fn parse_line(line: &mut LineCursor) -> Result<Self, ParseError> {
+        let mesh = line.word("a mesh name")?.text.to_string();
+        let texture = line.word("a texture name")?.text.to_string();
+        let mut half = Vec3::zero();
+        for (axis, what) in ["half x", "half y", "half z"].into_iter().enumerate() {
+            let column = line.column();
+            half[axis] = line.float(what)?;
+            if half[axis] <= 0. {
+                return Err(line.error(column, "half extents must be positive"));
+            }
+        }
+        let column = line.column();
+        let speed = line.float("a speed")?;
+        if speed < 0. {
+            return Err(line.error(column, "speed can't be negative"));
+        }
+        let mode = line.word("loop or pingpong")?;
+        let mode = match mode.text {
+            "loop" => PathMode::Loop,
+            "pingpong" => PathMode::PingPong,
+            other => return Err(line.error(mode.column, format!("unknown path mode {other:?}"))),
+        };
+        let mut path = vec![];
+        while line.peek().is_some() {
+            path.push(Vec3::new(line.float("waypoint x")?, line.float("waypoint y")?, line.float("waypoint z")?));
+        }
+        if path.is_empty() {
+            return Err(line.error(line.column(), "platform has no waypoints"));
+        }
+
+        Ok(Self {
+            mesh,
+            texture,
+            half,
+            speed,
+            mode,
+            path,
+        })
+    }
+
+    // Everywhere the platform goes, as one box.
+    pub fn swept_bounds(&self) -> BoundingBox {
+        self.path
+            .iter()
+            .map(|&p| BoundingBox::around(p, self.half))
+            .reduce(|a, b| a.union(&b))
+            .unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(text: &str) -> Result<PlatformSpec, ParseError> {
+        PlatformSpec::parse_line(&mut LineCursor::new("test_platforms.txt", 1, text))
+    }
+
+    #[test]
+    fn half_extents_must_be_positive() {
+        for (text, column) in [
+            ("box box 0 1 1 2 loop 0 0 0", 9),
+            ("box box 1 -1 1 2 loop 0 0 0", 11),
+            ("box box 1 1 0 2 loop 0 0 0", 13),
+        ] {
+            let e = parse(text).unwrap_err();
+            assert_eq!((e.line, e.column), (1, column), "{text}: {e}");
+        }
+        assert!(parse("box box 1 1 1 2 loop 0 0 0").is_ok());
+    }
+
+    // a route along x from 0 to 2 and back, at 1 unit/second
+    fn along_x(mode: PathMode) -> Route {
+        Route::new(vec![Vec3::zero(), Vec3::unit_x(), Vec3::unit_x() * 2.], 1., mode)
+    }
+
+    #[test]
+    fn ping_pong_turns_around_at_both_ends() {
+        let mut route = along_x(PathMode::PingPong);
+        route.step(2.5);
+        assert_eq!(route.pos, Vec3::unit_x() * 1.5);
+        route.step(2.);
+        assert_eq!(route.pos, Vec3::unit_x() * 0.5);
+        route.step(1.);
+        assert_eq!(route.pos, Vec3::unit_x() * 1.5);
+    }
+
+    #[test]
+    fn loop_wraps_back_to_the_start() {
+        let mut route = along_x(PathMode::Loop);
+        route.step(2.);
+        // the last leg goes straight back from the last waypoint to the first
+        route.step(1.5);
+        assert_eq!(route.pos, Vec3::unit_x() * 0.5);
+        // then on through the first waypoint toward the second
+        route.step(0.75);
+        assert_eq!(route.pos, Vec3::unit_x() * 0.25);
+        route.reset();
+        assert_eq!(route.pos, Vec3::zero());
+    }
+
+    #[test]
+    fn step_returns_the_distance_carried() {
+        let mut route = along_x(PathMode::PingPong);
+        assert_eq!(route.step(0.5), Vec3::unit_x() * 0.5);
+        route.step(1.);
+        // past the far end and back, only the net motion carries a rider
+        let start = route.pos;
+        let delta = route.step(1.);
+        assert_eq!(delta, Vec3::zero());
+        assert_eq!(route.pos, start + delta);
+    }
+}
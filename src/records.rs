@@ -0,0 +1,25 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+// Most collectibles ever picked up in a finished run of each level, keyed by
+// level name. Kept in the save file.
+#[derive(Debug, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Records {
+    pub best_collected: HashMap<String, usize>,
+}
+
+impl Records {
+    pub fn best(&self, level: &str) -> usize {
+        self.best_collected.get(level).copied().unwrap_or(0)
+    }
+
+    // Keeps `collected` if it beats the old record, returning whether it did.
+    pub fn submit(&mut self, level: &str, collected: usize) -> bool {
+        if collected <= self.best(level) {
+            return false;
+        }
+        self.best_collected.insert(level.to_string(), collected);
+        true
+    }
+}
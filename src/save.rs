@@ -0,0 +1,224 @@
+use crate::files::write_atomically;
+use crate::records::Records;
+use crate::speedrun::PersonalBests;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// Bumped whenever SaveData changes in a way older files can't be read as.
+// Fields that are only added don't need it, they fall back to their defaults.
+pub const SAVE_VERSION: u32 = 1;
+const SAVE_FILE: &str = "save.ron";
+
+#[derive(Clone, Copy, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Settings {
+    pub music_volume: f64,
+    pub effects_volume: f64,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            music_volume: 1.,
+            effects_volume: 1.,
+        }
+    }
+}
+
+// Everything kept from one launch to the next. Levels go by name rather than
+// position so reordering the manifest doesn't mix them up.
+#[derive(Debug, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct SaveData {
+    pub version: u32,
+    pub level: Option<String>, // where to carry on from
+    pub unlocked: Vec<String>, // the first level always is, so it isn't listed
+    pub records: Records,
+    pub bests: PersonalBests,
+    pub settings: Settings,
+}
+
+impl Default for SaveData {
+    fn default() -> Self {
+        Self {
+            version: SAVE_VERSION,
+            level: None,
+            unlocked: vec![],
+            records: Records::default(),
+            bests: PersonalBests::default(),
+            settings: Settings::default(),
+        }
+    }
+}
+
+// just enough of a save to tell which version wrote it
+#[derive(Deserialize)]
+struct Version {
+    version: u32,
+}
+
+// save.ron in the platform's config dir, e.g. ~/.config/jumpyball/ on Linux.
+pub fn default_path() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join("jumpyball").join(SAVE_FILE))
+}
+
+impl SaveData {
+    pub fn is_unlocked(&self, level: &str) -> bool {
+        self.unlocked.iter().any(|l| l == level)
+    }
+
+    // Returns whether `level` wasn't already unlocked.
+    pub fn unlock(&mut self, level: &str) -> bool {
+        if self.is_unlocked(level) {
+            return false;
+        }
+        self.unlocked.push(level.to_string());
+        true
+    }
+
+    pub fn from_ron(src: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        let Version { version } = ron::from_str(src)?;
+        match version {
+            SAVE_VERSION => Ok(ron::from_str(src)?),
+            // older versions get converted here as the format changes
+            v if v > SAVE_VERSION => Err(format!("written by a newer version of the game (save version {v})").into()),
+            v => Err(format!("unknown save version {v}").into()),
+        }
+    }
+
+    // None when there's no save yet.
+    pub fn from_file(path: &Path) -> Result<Option<Self>, Box<dyn std::error::Error>> {
+        let src = match std::fs::read_to_string(path) {
+            Ok(src) => src,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(format!("{}: {e}", path.display()).into()),
+        };
+        Self::from_ron(&src)
+            .map(Some)
+            .map_err(|e| format!("{}: {e}", path.display()).into())
+    }
+
+    // Loads the save at `path`, None if there isn't a usable one. A save that
+    // can't be read is moved aside, so it isn't overwritten and can be looked
+    // at later, and the second value says what happened to it.
+    pub fn load_or_back_up(path: &Path) -> (Option<Self>, Option<String>) {
+        let error = match Self::from_file(path) {
+            Ok(save) => return (save, None),
+            Err(error) => error,
+        };
+        let secs = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs());
+        let mut backup = path.as_os_str().to_owned();
+        backup.push(format!(".corrupt-{secs}"));
+        let backup = PathBuf::from(backup);
+        let notice = match std::fs::rename(path, &backup) {
+            Ok(()) => format!("couldn't read the save, so it was moved to {}: {error}", backup.display()),
+            Err(e) => format!("couldn't read the save or move it aside ({e}): {error}"),
+        };
+        (None, Some(notice))
+    }
+
+    // Never leaves a half-written save behind, see `write_atomically`.
+    pub fn save(&self, path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir).map_err(|e| format!("{}: {e}", dir.display()))?;
+        }
+        let ron = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::new())?;
+        write_atomically(path, &ron)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // An empty directory of its own for each test, removed when it's done.
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new(name: &str) -> Self {
+            let dir = std::env::temp_dir().join(format!("jumpyball_{name}_{}", std::process::id()));
+            let _ = std::fs::remove_dir_all(&dir);
+            std::fs::create_dir_all(&dir).unwrap();
+            Self(dir)
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn files_in(dir: &Path) -> Vec<PathBuf> {
+        let mut files: Vec<PathBuf> = std::fs::read_dir(dir).unwrap().map(|e| e.unwrap().path()).collect();
+        files.sort();
+        files
+    }
+
+    #[test]
+    fn no_save_yet_is_not_a_problem() {
+        let dir = TempDir::new("no_save");
+        let (save, notice) = SaveData::load_or_back_up(&dir.0.join(SAVE_FILE));
+        assert_eq!((save, notice), (None, None));
+    }
+
+    #[test]
+    fn garbage_is_moved_aside() {
+        let dir = TempDir::new("garbage");
+        let path = dir.0.join(SAVE_FILE);
+        std::fs::write(&path, "}} not a save at all").unwrap();
+        let (save, notice) = SaveData::load_or_back_up(&path);
+        assert_eq!(save, None);
+        let notice = notice.expect("nothing said about the broken save");
+        assert!(notice.contains("moved to"), "{notice}");
+
+        let files = files_in(&dir.0);
+        assert_eq!(files.len(), 1, "{files:?}");
+        assert!(files[0].to_string_lossy().contains(".corrupt-"), "{files:?}");
+        assert_eq!(std::fs::read_to_string(&files[0]).unwrap(), "}} not a save at all");
+    }
+
+    #[test]
+    fn saves_without_a_known_version_are_rejected() {
+        let e = SaveData::from_ron("(level: Some(\"one\"))").unwrap_err();
+        assert!(e.to_string().contains("version"), "{e}");
+        let future = format!("(version: {})", SAVE_VERSION + 1);
+        let e = SaveData::from_ron(&future).unwrap_err();
+        assert!(e.to_string().contains("newer version"), "{e}");
+
+        // and moved aside like any other unreadable save
+        let dir = TempDir::new("future");
+        let path = dir.0.join(SAVE_FILE);
+        std::fs::write(&path, &future).unwrap();
+        let (save, notice) = SaveData::load_or_back_up(&path);
+        assert_eq!(save, None);
+        assert!(notice.is_some_and(|n| n.contains("newer version")));
+        assert!(!path.exists());
+    }
+
+    #[test]
+    fn saved_data_loads_back_the_same() {
+        let dir = TempDir::new("round_trip");
+        // the config directory might not exist yet either
+        let path = dir.0.join("jumpyball").join(SAVE_FILE);
+        let mut save = SaveData {
+            level: Some("two".to_string()),
+            settings: Settings {
+                music_volume: 0.25,
+                effects_volume: 0.5,
+            },
+            ..SaveData::default()
+        };
+        save.unlock("two");
+        save.records.submit("one", 3);
+        save.bests.submit_level("one", 1234);
+        save.bests.submit_run(&[1234, 567]);
+        save.save(&path).unwrap();
+        assert_eq!(SaveData::from_file(&path).unwrap(), Some(save));
+        // only the save itself is left, no temporary file
+        assert_eq!(files_in(path.parent().unwrap()), vec![path.clone()]);
+    }
+}
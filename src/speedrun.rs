@@ -0,0 +1,294 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+// Times a run in simulation ticks, so it comes out the same whatever the
+// frame rate. A run is split into the levels it went through; each split is
+// the ticks from the end of the last one, restarts included.
+#[derive(Clone, Debug, Default)]
+pub struct RunTimer {
+    ticks: u64,
+    split_start: u64,
+    splits: Vec<u64>,
+    first_level: usize,
+    running: bool,
+    // cleared when something like the editor makes the time meaningless
+    level_counts: bool,
+    run_counts: bool,
+}
+
+impl RunTimer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Starts over from nothing at `first_level`. Only runs from the first
+    // level can set a best for the whole run.
+    pub fn start(&mut self, first_level: usize) {
+        *self = Self {
+            first_level,
+            running: true,
+            level_counts: true,
+            run_counts: true,
+            ..Self::default()
+        };
+    }
+
+    pub fn stop(&mut self) {
+        self.running = false;
+    }
+
+    pub fn tick(&mut self) {
+        if self.running {
+            self.ticks += 1;
+        }
+    }
+
+    // Ends the current level's split, returning how long it took.
+    pub fn split(&mut self) -> u64 {
+        let level = self.level_ticks();
+        self.splits.push(level);
+        self.split_start = self.ticks;
+        self.level_counts = self.run_counts;
+        level
+    }
+
+    // Nothing from this level on is good for a best time.
+    pub fn disqualify(&mut self) {
+        self.level_counts = false;
+        self.run_counts = false;
+    }
+
+    pub fn ticks(&self) -> u64 {
+        self.ticks
+    }
+
+    pub fn level_ticks(&self) -> u64 {
+        self.ticks - self.split_start
+    }
+
+    pub fn splits(&self) -> &[u64] {
+        &self.splits
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.running
+    }
+
+    // whether the level being played can still set a best time
+    pub fn level_counts(&self) -> bool {
+        self.level_counts
+    }
+
+    // Whether the run went through all `levels` from the first, untouched.
+    pub fn is_full_run(&self, levels: usize) -> bool {
+        self.first_level == 0 && self.splits.len() == levels && self.run_counts
+    }
+
+    // Ticks ahead (negative) or behind (positive) the best run `best`. Known
+    // at every split, and in between once the current level has gone past
+    // where the best run had already finished it. None when there's nothing
+    // to compare against yet.
+    pub fn delta(&self, best: &[u64]) -> Option<i64> {
+        if self.first_level != 0 || best.is_empty() || self.splits.len() > best.len() {
+            return None;
+        }
+        let done = self.splits.len();
+        let best_through = |n: usize| best[..n].iter().sum::<u64>() as i64;
+        if done < best.len() && self.ticks as i64 > best_through(done + 1) {
+            return Some(self.ticks as i64 - best_through(done + 1));
+        }
+        (done > 0).then(|| self.split_start as i64 - best_through(done))
+    }
+}
+
+// m:ss.cc for a number of ticks `dt` seconds long.
+pub fn format_time(ticks: u64, dt: f64) -> String {
+    let centis = (ticks as f64 * dt * 100.).round() as u64;
+    format!("{}:{:02}.{:02}", centis / 6000, centis / 100 % 60, centis % 100)
+}
+
+// A signed difference, like -1.25 or +0:03.50.
+pub fn format_delta(delta: i64, dt: f64) -> String {
+    let sign = if delta < 0 { '-' } else { '+' };
+    let centis = (delta.unsigned_abs() as f64 * dt * 100.).round() as u64;
+    if centis < 6000 {
+        format!("{sign}{}.{:02}", centis / 100, centis % 100)
+    } else {
+        format!("{sign}{}", format_time(delta.unsigned_abs(), dt))
+    }
+}
+
+// Fastest time for each level by name, and the splits of the fastest run
+// through the whole campaign, all in ticks. Kept in the save file.
+#[derive(Debug, Default, PartialEq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct PersonalBests {
+    pub levels: HashMap<String, u64>,
+    pub run: Vec<u64>, // empty until a full run is finished
+}
+
+impl PersonalBests {
+    pub fn level(&self, name: &str) -> Option<u64> {
+        self.levels.get(name).copied()
+    }
+
+    pub fn run_total(&self) -> Option<u64> {
+        (!self.run.is_empty()).then(|| self.run.iter().sum())
+    }
+
+    // Keeps `ticks` if it beats the level's best, returning whether it did.
+    pub fn submit_level(&mut self, name: &str, ticks: u64) -> bool {
+        if self.level(name).is_some_and(|best| best <= ticks) {
+            return false;
+        }
+        self.levels.insert(name.to_string(), ticks);
+        true
+    }
+
+    // Keeps a finished run's splits if it's faster than the best run, or if
+    // the best run went through a different number of levels.
+    pub fn submit_run(&mut self, splits: &[u64]) -> bool {
+        let total: u64 = splits.iter().sum();
+        if self.run.len() == splits.len() && self.run_total().is_some_and(|best| best <= total) {
+            return false;
+        }
+        self.run = splits.to_vec();
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DT: f64 = 1. / 60.;
+
+    fn tick(timer: &mut RunTimer, ticks: u64) {
+        (0..ticks).for_each(|_| timer.tick());
+    }
+
+    #[test]
+    fn ticks_only_count_while_running() {
+        let mut timer = RunTimer::new();
+        tick(&mut timer, 5);
+        assert_eq!(timer.ticks(), 0);
+        timer.start(0);
+        tick(&mut timer, 5);
+        timer.stop();
+        tick(&mut timer, 5);
+        assert_eq!(timer.ticks(), 5);
+    }
+
+    #[test]
+    fn splits_are_the_time_since_the_last_one() {
+        let mut timer = RunTimer::new();
+        timer.start(0);
+        tick(&mut timer, 30);
+        assert_eq!(timer.split(), 30);
+        tick(&mut timer, 12);
+        assert_eq!(timer.level_ticks(), 12);
+        assert_eq!(timer.split(), 12);
+        assert_eq!(timer.splits(), &[30, 12]);
+        assert_eq!(timer.ticks(), 42);
+    }
+
+    #[test]
+    fn delta_waits_until_the_best_split_is_passed() {
+        let best = [100, 50];
+        let mut timer = RunTimer::new();
+        timer.start(0);
+        tick(&mut timer, 50);
+        assert_eq!(timer.delta(&best), None);
+        tick(&mut timer, 50);
+        assert_eq!(timer.delta(&best), None);
+        tick(&mut timer, 1);
+        assert_eq!(timer.delta(&best), Some(1));
+        tick(&mut timer, 9);
+        timer.split();
+        assert_eq!(timer.delta(&best), Some(10));
+        // still behind by the last split's margin until the next best split
+        tick(&mut timer, 40);
+        assert_eq!(timer.delta(&best), Some(10));
+        tick(&mut timer, 5);
+        assert_eq!(timer.delta(&best), Some(5));
+    }
+
+    #[test]
+    fn delta_shows_time_ahead_at_a_split() {
+        let mut timer = RunTimer::new();
+        timer.start(0);
+        tick(&mut timer, 90);
+        timer.split();
+        assert_eq!(timer.delta(&[100, 50]), Some(-10));
+        assert_eq!(timer.delta(&[]), None);
+        // runs from a later level have nothing to compare against
+        timer.start(1);
+        tick(&mut timer, 200);
+        assert_eq!(timer.delta(&[100, 50]), None);
+    }
+
+    #[test]
+    fn full_runs_start_at_the_first_level_and_finish_every_one() {
+        let mut timer = RunTimer::new();
+        timer.start(0);
+        timer.split();
+        assert!(!timer.is_full_run(2));
+        timer.split();
+        assert!(timer.is_full_run(2));
+
+        timer.start(1);
+        timer.split();
+        assert!(!timer.is_full_run(1));
+    }
+
+    #[test]
+    fn disqualified_runs_dont_count() {
+        let mut timer = RunTimer::new();
+        timer.start(0);
+        assert!(timer.level_counts());
+        timer.disqualify();
+        assert!(!timer.level_counts());
+        timer.split();
+        // nor does any level after it
+        assert!(!timer.level_counts());
+        timer.split();
+        assert!(!timer.is_full_run(2));
+    }
+
+    #[test]
+    fn level_bests_are_only_replaced_when_beaten() {
+        let mut bests = PersonalBests::default();
+        assert!(bests.submit_level("one", 100));
+        assert!(!bests.submit_level("one", 120));
+        assert!(!bests.submit_level("one", 100));
+        assert_eq!(bests.level("one"), Some(100));
+        assert!(bests.submit_level("one", 90));
+        assert_eq!(bests.level("one"), Some(90));
+        assert_eq!(bests.level("two"), None);
+    }
+
+    #[test]
+    fn run_bests_are_only_replaced_when_beaten() {
+        let mut bests = PersonalBests::default();
+        assert_eq!(bests.run_total(), None);
+        assert!(bests.submit_run(&[100, 50]));
+        assert!(!bests.submit_run(&[60, 100]));
+        assert!(!bests.submit_run(&[75, 75]));
+        assert!(bests.submit_run(&[120, 20]));
+        assert_eq!(bests.run, vec![120, 20]);
+        assert_eq!(bests.run_total(), Some(140));
+        // a campaign with another number of levels starts over
+        assert!(bests.submit_run(&[500, 500, 500]));
+    }
+
+    #[test]
+    fn times_read_as_minutes_seconds_and_hundredths() {
+        assert_eq!(format_time(0, DT), "0:00.00");
+        assert_eq!(format_time(90, DT), "0:01.50");
+        assert_eq!(format_time(7530, DT), "2:05.50");
+        assert_eq!(format_delta(-75, DT), "-1.25");
+        assert_eq!(format_delta(210, DT), "+3.50");
+        assert_eq!(format_delta(0, DT), "+0.00");
+        assert_eq!(format_delta(3900, DT), "+1:05.00");
+    }
+}
@@ -0,0 +1,235 @@
+use crate::campaign::Campaign;
+use crate::speedrun::{PersonalBests, RunTimer};
+
+// The screens the game moves between. Each gets its own update and render
+// path in the game's World.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GameState {
+    Title,
+    Playing,
+    Paused,
+    LevelComplete, // summary of the level just finished
+    Credits,       // the last level is done
+}
+
+// Things that happen to move the game between states.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StateEvent {
+    Start, // leave the title screen
+    Pause,
+    Resume,
+    RestartLevel, // from the pause screen
+    QuitToTitle,  // give up on the run from the pause screen
+    LevelFinished,
+    NextLevel,        // done with the summary and there are levels left
+    CampaignFinished, // done with the summary of the last level
+    BackToTitle,      // done with the credits
+}
+
+impl GameState {
+    // Where `event` leads from this state, or None when it means nothing here.
+    // Kept free of the rest of the game so the flow between screens can be
+    // checked on its own.
+    pub fn on(self, event: StateEvent) -> Option<Self> {
+        use GameState::*;
+        use StateEvent::*;
+        match (self, event) {
+            (Title, Start) => Some(Playing),
+            (Playing, Pause) => Some(Paused),
+            (Paused, Resume) => Some(Playing),
+            (Paused, RestartLevel) => Some(Playing),
+            (Paused, QuitToTitle) => Some(Title),
+            (Playing, LevelFinished) => Some(LevelComplete),
+            (LevelComplete, NextLevel) => Some(Playing),
+            (LevelComplete, CampaignFinished) => Some(Credits),
+            (Credits, BackToTitle) => Some(Title),
+            _ => None,
+        }
+    }
+
+    // whether the ball is on screen, as opposed to an empty level behind a menu
+    pub fn shows_player(self) -> bool {
+        matches!(self, GameState::Playing | GameState::Paused | GameState::LevelComplete)
+    }
+}
+
+// What a state change did to the run that the caller has to follow up on.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct RunChanges {
+    pub respawn: bool,  // a level started over or a new one began
+    pub improved: bool, // a best time was beaten and is worth saving
+}
+
+// The part of a state change that's about the run rather than the screen:
+// moving through the campaign, stopping and splitting the timer and keeping
+// any new bests. `name` gives a level's name for its best time. Call it only
+// for events that `GameState::on` accepts.
+pub fn apply_to_run<L>(
+    event: StateEvent,
+    campaign: &mut Campaign<L>,
+    timer: &mut RunTimer,
+    bests: &mut PersonalBests,
+    name: impl Fn(&L) -> &str,
+) -> RunChanges {
+    let mut changes = RunChanges::default();
+    // ends the timer's split for the level just finished
+    let mut split = |campaign: &Campaign<L>, timer: &mut RunTimer| {
+        let counts = timer.level_counts();
+        let ticks = timer.split();
+        changes.improved = counts && bests.submit_level(name(campaign.current()), ticks);
+        if timer.is_full_run(campaign.len()) {
+            changes.improved |= bests.submit_run(timer.splits());
+        }
+    };
+    match event {
+        StateEvent::Start => timer.start(campaign.index()),
+        StateEvent::NextLevel => {
+            split(campaign, timer);
+            changes.respawn = campaign.advance();
+        }
+        StateEvent::CampaignFinished => {
+            split(campaign, timer);
+            timer.stop();
+        }
+        // the clock keeps running, a restart costs time like a fall does
+        StateEvent::RestartLevel => changes.respawn = campaign.select(campaign.index()),
+        // quitting keeps the player's place, the campaign only starts over
+        // once it's finished
+        StateEvent::QuitToTitle => {
+            timer.stop();
+            changes.respawn = campaign.select(campaign.index());
+        }
+        StateEvent::BackToTitle => {
+            timer.stop();
+            changes.respawn = campaign.select(0);
+        }
+        StateEvent::Pause | StateEvent::Resume | StateEvent::LevelFinished => {}
+    }
+    changes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::GameState::*;
+    use super::StateEvent::*;
+    use super::*;
+
+    // Follows `events` from `state` through `on`, checking each one leads
+    // somewhere, and returns every state along the way.
+    fn walk(mut state: GameState, events: &[StateEvent]) -> Vec<GameState> {
+        let mut states = vec![state];
+        for &event in events {
+            state = state
+                .on(event)
+                .unwrap_or_else(|| panic!("{event:?} on {state:?} after {states:?}"));
+            states.push(state);
+        }
+        states
+    }
+
+    #[test]
+    fn playing_through_a_level() {
+        let states = walk(Title, &[Start, Pause, RestartLevel, LevelFinished, NextLevel]);
+        assert_eq!(states, vec![Title, Playing, Paused, Playing, LevelComplete, Playing]);
+        // the summary can't be paused, nor a level finished from the pause screen
+        assert_eq!(LevelComplete.on(Pause), None);
+        assert_eq!(Paused.on(LevelFinished), None);
+    }
+
+    #[test]
+    fn quitting_from_the_pause_screen() {
+        let states = walk(Title, &[Start, Pause, QuitToTitle]);
+        assert_eq!(states, vec![Title, Playing, Paused, Title]);
+        assert_eq!(Title.on(Resume), None);
+    }
+
+    #[test]
+    fn finishing_the_campaign() {
+        let states = walk(Playing, &[LevelFinished, CampaignFinished, BackToTitle]);
+        assert_eq!(states, vec![Playing, LevelComplete, Credits, Title]);
+        assert_eq!(Credits.on(NextLevel), None);
+    }
+
+    struct Run {
+        campaign: Campaign<String>,
+        timer: RunTimer,
+        bests: PersonalBests,
+    }
+
+    impl Run {
+        fn new() -> Self {
+            Self {
+                campaign: Campaign::new(vec!["one".to_string(), "two".to_string()]),
+                timer: RunTimer::new(),
+                bests: PersonalBests::default(),
+            }
+        }
+
+        fn apply(&mut self, event: StateEvent) -> RunChanges {
+            apply_to_run(event, &mut self.campaign, &mut self.timer, &mut self.bests, |l| {
+                l.as_str()
+            })
+        }
+
+        // plays the current level to its goal in `ticks`
+        fn play(&mut self, ticks: u64) {
+            (0..ticks).for_each(|_| self.timer.tick());
+            self.campaign.complete_level();
+        }
+    }
+
+    #[test]
+    fn a_full_run_advances_the_campaign_and_splits_the_timer() {
+        let mut run = Run::new();
+        assert_eq!(run.apply(Start), RunChanges::default());
+        assert!(run.timer.is_running());
+
+        run.play(10);
+        run.apply(LevelFinished);
+        let changes = run.apply(NextLevel);
+        assert_eq!(
+            changes,
+            RunChanges {
+                respawn: true,
+                improved: true
+            }
+        );
+        assert_eq!(run.campaign.index(), 1);
+        assert_eq!(run.timer.splits(), &[10]);
+        assert_eq!(run.bests.level("one"), Some(10));
+
+        run.play(5);
+        run.apply(LevelFinished);
+        let changes = run.apply(CampaignFinished);
+        assert_eq!(
+            changes,
+            RunChanges {
+                respawn: false,
+                improved: true
+            }
+        );
+        assert!(!run.timer.is_running());
+        assert_eq!(run.bests.run, vec![10, 5]);
+
+        assert!(run.apply(BackToTitle).respawn);
+        assert_eq!(run.campaign.index(), 0);
+    }
+
+    #[test]
+    fn restarting_and_quitting_keep_the_players_place() {
+        let mut run = Run::new();
+        run.campaign.select(1);
+        run.apply(Start);
+        run.timer.tick();
+        assert!(run.apply(RestartLevel).respawn);
+        assert_eq!(run.campaign.index(), 1);
+        // a restart costs time, it doesn't reset the clock
+        assert_eq!(run.timer.ticks(), 1);
+        assert!(run.timer.is_running());
+
+        assert!(run.apply(QuitToTitle).respawn);
+        assert_eq!(run.campaign.index(), 1);
+        assert!(!run.timer.is_running());
+        assert!(run.timer.splits().is_empty());
+    }
+}
@@ -0,0 +1,331 @@
+use crate::physics::{Collider, Colliders, Shape};
+use crate::platform::PlatformSpec;
+use frenderer::types::*;
+use std::fmt;
+
+// later jumps are tried at this many points along the arc before them
+const JUMP_SAMPLES_PER_SECOND: f32 = 120.;
+// triangles steeper than this are walls, same as the physics' ground test
+const WALKABLE_NORMAL_Y: f32 = 0.7;
+// a spawn resting right on the ground shouldn't count as inside it
+const SPAWN_SLACK: f32 = 0.05;
+
+// What the ball can do in the air, taken from the game's movement settings.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct JumpPhysics {
+    pub radius: f32,
+    pub top_speed: f32,  // units/second
+    pub jump_speed: f32, // units/second
+    pub gravity: f32,    // units/second^2, negative is down
+    pub jumps: u8,       // before landing again, 2 is a double jump
+    // half size of the box around a goal or collectible the ball has to touch
+    pub pickup_half: Vec3,
+}
+
+impl JumpPhysics {
+    // Highest the ball's center can get above where it jumped from.
+    pub fn max_rise(&self) -> f32 {
+        self.jumps as f32 * self.jump_speed * self.jump_speed / (-2. * self.gravity)
+    }
+
+    // Longest the ball can stay in the air on a jump that ends `dy` above
+    // where it started, using every jump at the best moment, or None if it
+    // can't get that high.
+    pub fn air_time(&self, dy: f32) -> Option<f32> {
+        if self.jumps == 0 {
+            self.ballistic(0., 0., dy)
+        } else {
+            self.flight(0., self.jump_speed, self.jumps - 1, dy)
+        }
+    }
+
+    // Whether the ball can cross `gap` units sideways while ending `dy` higher.
+    pub fn can_cross(&self, gap: f32, dy: f32) -> bool {
+        self.air_time(dy).is_some_and(|t| t * self.top_speed >= gap)
+    }
+
+    // time until a ball at height `y` going up at `vy` comes down through `dy`
+    fn ballistic(&self, y: f32, vy: f32, dy: f32) -> Option<f32> {
+        let disc = vy * vy - 2. * self.gravity * (y - dy);
+        if disc < 0. {
+            return None;
+        }
+        let t = (-vy - disc.sqrt()) / self.gravity;
+        (t >= 0.).then_some(t)
+    }
+
+    fn flight(&self, y: f32, vy: f32, jumps_left: u8, dy: f32) -> Option<f32> {
+        let mut best = self.ballistic(y, vy, dy);
+        if jumps_left == 0 {
+            return best;
+        }
+        // past this point even the remaining jumps can't climb back to dy
+        let recover = jumps_left as f32 * self.jump_speed * self.jump_speed / (-2. * self.gravity);
+        let end = match self.ballistic(y, vy, dy - recover) {
+            Some(end) => end,
+            None => return best,
+        };
+        let steps = (end * JUMP_SAMPLES_PER_SECOND).ceil() as usize;
+        for step in 0..=steps {
+            let s = step as f32 / JUMP_SAMPLES_PER_SECOND;
+            let ys = y + vy * s + self.gravity * s * s / 2.;
+            if let Some(t) = self.flight(ys, self.jump_speed, jumps_left - 1, dy) {
+                best = Some(best.map_or(s + t, |b| b.max(s + t)));
+            }
+        }
+        best
+    }
+}
+
+// Something wrong with a level.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Problem {
+    SpawnInside(Collider),
+    SpawnOverNothing,
+    GoalInside(usize, Collider),
+    GoalUnreachable(usize),
+    CollectibleUnreachable(usize),
+    Unreachable(Collider), // a box or platform the ball can never stand on
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Problem::SpawnInside(c) => write!(f, "spawn is inside {}", describe(*c)),
+            Problem::SpawnOverNothing => write!(f, "spawn has nothing under it"),
+            Problem::GoalInside(i, c) => write!(f, "goal {i} is inside {}", describe(*c)),
+            Problem::GoalUnreachable(i) => write!(f, "goal {i} can't be reached from the spawn"),
+            Problem::CollectibleUnreachable(i) => write!(f, "collectible {i} can't be reached from the spawn"),
+            Problem::Unreachable(c) => write!(f, "{} can't be reached from the spawn", describe(*c)),
+        }
+    }
+}
+
+fn describe(c: Collider) -> String {
+    match c {
+        Collider::Level(i) => format!("collision shape {i}"),
+        Collider::Platform(i) => format!("platform {i}"),
+    }
+}
+
+// Top of something the ball can stand on: its footprint on x/z, and the
+// heights it can be stood at. Ramps and moving platforms cover a range.
+#[derive(Clone, Copy, Debug)]
+struct Surface {
+    collider: Collider,
+    min: Vec2,
+    max: Vec2,
+    low: f32,
+    high: f32,
+}
+
+impl Surface {
+    fn of_shape(i: usize, shape: &Shape) -> Option<Self> {
+        let b = shape.bounds();
+        let low = match shape {
+            Shape::Wedge(_) => b.min().y,
+            Shape::Triangle(t) => {
+                let n = (t.b - t.a).cross(t.c - t.a).normalized();
+                if n.y.abs() < WALKABLE_NORMAL_Y {
+                    return None;
+                }
+                b.min().y
+            }
+            _ => b.max().y,
+        };
+        Some(Self {
+            collider: Collider::Level(i),
+            min: Vec2::new(b.min().x, b.min().z),
+            max: Vec2::new(b.max().x, b.max().z),
+            low,
+            high: b.max().y,
+        })
+    }
+
+    fn of_platform(i: usize, spec: &PlatformSpec) -> Self {
+        let b = spec.swept_bounds();
+        Self {
+            collider: Collider::Platform(i),
+            min: Vec2::new(b.min().x, b.min().z),
+            max: Vec2::new(b.max().x, b.max().z),
+            low: b.min().y + 2. * spec.half.y,
+            high: b.max().y,
+        }
+    }
+
+    // sideways distance from the footprint to a point
+    fn gap_to(&self, p: Vec2) -> f32 {
+        (p.max_by_component(self.min).min_by_component(self.max) - p).mag()
+    }
+
+    // sideways distance between two footprints
+    fn gap_between(&self, other: &Self) -> f32 {
+        let d = (other.min - self.max).max_by_component(self.min - other.max);
+        d.max_by_component(Vec2::zero()).mag()
+    }
+}
+
+// Checks the spawn and goals against the level's shapes, then works out
+// which surfaces the ball can get to from the spawn with `physics` and
+// reports the goals, collectibles, boxes and platforms it can't. Platforms
+// count as everywhere along their path, so this is an optimistic estimate:
+// anything reported really is out of reach, but not everything left
+// unreported is guaranteed to be reachable.
+pub fn check_level(
+    physics: &JumpPhysics,
+    colliders: &Colliders,
+    platforms: &[PlatformSpec],
+    spawn: Vec3,
+    goals: &[Vec3],
+    collectibles: &[Vec3],
+) -> Vec<Problem> {
+    let mut problems = vec![];
+
+    if let Some(&c) = colliders.overlapping_sphere(spawn, physics.radius - SPAWN_SLACK).first() {
+        problems.push(Problem::SpawnInside(c));
+    }
+    for (i, &goal) in goals.iter().enumerate() {
+        if let Some(&c) = colliders.overlapping_sphere(goal, 0.).first() {
+            problems.push(Problem::GoalInside(i, c));
+        }
+    }
+
+    let surfaces: Vec<Surface> = colliders
+        .shapes
+        .iter()
+        .enumerate()
+        .filter_map(|(i, s)| Surface::of_shape(i, s))
+        .chain(platforms.iter().enumerate().map(|(i, p)| Surface::of_platform(i, p)))
+        .collect();
+
+    let depth = surfaces.iter().map(|s| spawn.y - s.low).fold(0., f32::max) + 1.;
+    let landing = colliders
+        .ray_cast(spawn, -Vec3::unit_y() * depth)
+        .and_then(|hit| surfaces.iter().position(|s| s.collider == hit.collider));
+    let start = match landing {
+        Some(start) => start,
+        None => {
+            problems.push(Problem::SpawnOverNothing);
+            return problems;
+        }
+    };
+
+    // flood out from wherever the spawn drops the ball
+    let mut reached = vec![false; surfaces.len()];
+    reached[start] = true;
+    let mut open = vec![start];
+    while let Some(from) = open.pop() {
+        let a = &surfaces[from];
+        for (to, b) in surfaces.iter().enumerate() {
+            if !reached[to] && physics.can_cross(a.gap_between(b), b.low - a.high) {
+                reached[to] = true;
+                open.push(to);
+            }
+        }
+    }
+
+    let can_take = |p: Vec3| {
+        let reach = physics.pickup_half.x.max(physics.pickup_half.z) + physics.radius;
+        surfaces.iter().zip(reached.iter()).any(|(s, &r)| {
+            // lowest the ball's center can be and still touch it
+            let dy = p.y - physics.pickup_half.y - physics.radius - (s.high + physics.radius);
+            r && physics.can_cross((s.gap_to(Vec2::new(p.x, p.z)) - reach).max(0.), dy)
+        })
+    };
+    for (i, &goal) in goals.iter().enumerate() {
+        if !can_take(goal) {
+            problems.push(Problem::GoalUnreachable(i));
+        }
+    }
+    for (i, &c) in collectibles.iter().enumerate() {
+        if !can_take(c) {
+            problems.push(Problem::CollectibleUnreachable(i));
+        }
+    }
+    // single triangles of a mesh are too fine grained to be worth listing
+    for (s, _) in surfaces.iter().zip(reached.iter()).filter(|(_, &r)| !r) {
+        let is_triangle = match s.collider {
+            Collider::Level(i) => matches!(colliders.shapes[i], Shape::Triangle(_)),
+            Collider::Platform(_) => false,
+        };
+        if !is_triangle {
+            problems.push(Problem::Unreachable(s.collider));
+        }
+    }
+
+    problems
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::physics::BoundingBox;
+
+    // the game's own settings: one jump rises 6 and carries 8 sideways on
+    // the flat, two rise 12
+    const PHYSICS: JumpPhysics = JumpPhysics {
+        radius: 1.,
+        top_speed: 12.,
+        jump_speed: 36.,
+        gravity: -108.,
+        jumps: 2,
+        pickup_half: Vec3::new(0.5, 1., 0.5),
+    };
+    const SINGLE_JUMP: JumpPhysics = JumpPhysics { jumps: 1, ..PHYSICS };
+    const SPAWN: Vec3 = Vec3::new(-5., 2., 0.);
+
+    // a floor from x -10 to 0 with its top at y 1, and then `more`
+    fn level(more: &[BoundingBox]) -> Colliders {
+        let floor = BoundingBox::new(-10., 0., 0., 1., -5., 5.);
+        Colliders::new(std::iter::once(floor).chain(more.iter().copied()).map(Shape::from).collect())
+    }
+
+    // a box like the floor, `gap` past its end and `rise` higher
+    fn ledge(gap: f32, rise: f32) -> BoundingBox {
+        BoundingBox::new(gap, gap + 10., 0., 1. + rise, -5., 5.)
+    }
+
+    #[test]
+    fn buried_spawn_is_reported() {
+        let problems = check_level(&PHYSICS, &level(&[]), &[], Vec3::new(-5., 0.5, 0.), &[], &[]);
+        assert_eq!(problems.first(), Some(&Problem::SpawnInside(Collider::Level(0))));
+        assert_eq!(check_level(&PHYSICS, &level(&[]), &[], SPAWN, &[], &[]), vec![]);
+    }
+
+    #[test]
+    fn goal_past_double_jump_reach_is_reported() {
+        let near = Vec3::new(6., 2.5, 0.);
+        let far = Vec3::new(40., 2.5, 0.);
+        let problems = check_level(&PHYSICS, &level(&[]), &[], SPAWN, &[near, far], &[]);
+        assert_eq!(problems, vec![Problem::GoalUnreachable(1)]);
+    }
+
+    #[test]
+    fn gap_inside_single_jump_reach() {
+        let colliders = level(&[ledge(7.5, 0.)]);
+        assert_eq!(check_level(&SINGLE_JUMP, &colliders, &[], SPAWN, &[], &[]), vec![]);
+        assert_eq!(check_level(&PHYSICS, &colliders, &[], SPAWN, &[], &[]), vec![]);
+    }
+
+    #[test]
+    fn gap_that_needs_the_second_jump() {
+        let colliders = level(&[ledge(12., 0.)]);
+        assert_eq!(
+            check_level(&SINGLE_JUMP, &colliders, &[], SPAWN, &[], &[]),
+            vec![Problem::Unreachable(Collider::Level(1))]
+        );
+        assert_eq!(check_level(&PHYSICS, &colliders, &[], SPAWN, &[], &[]), vec![]);
+    }
+
+    #[test]
+    fn platform_above_max_rise_is_reported() {
+        assert_eq!(PHYSICS.max_rise(), 12.);
+        let colliders = level(&[ledge(0., 11.)]);
+        assert_eq!(check_level(&PHYSICS, &colliders, &[], SPAWN, &[], &[]), vec![]);
+        let colliders = level(&[ledge(0., 13.)]);
+        assert_eq!(
+            check_level(&PHYSICS, &colliders, &[], SPAWN, &[], &[]),
+            vec![Problem::Unreachable(Collider::Level(1))]
+        );
+    }
+}